@@ -0,0 +1,231 @@
+use crate::game::UserId;
+use crate::session::{self, UserTokens};
+use crate::storage::{self, AccountRecord, SharedStorage};
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, StatusCode, header::AUTHORIZATION},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::OnceCell;
+use tracing::{error, info};
+use uuid::Uuid;
+
+const MIN_USERNAME_LENGTH: usize = 3;
+const MAX_USERNAME_LENGTH: usize = 24;
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_PASSWORD_LENGTH: usize = 128;
+
+/// Logins for unknown usernames are checked against this so they take as long as a wrong password.
+static DUMMY_PASSWORD_HASH: OnceCell<Option<String>> = OnceCell::const_new();
+
+/// What the account routes need from the app state.
+#[derive(Debug, Clone)]
+pub struct AuthState {
+    pub storage: SharedStorage,
+    pub user_tokens: UserTokens,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Credentials {
+    username: String,
+    password: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthResponse {
+    user_id: UserId,
+    username: String,
+    token: String,
+}
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("{0}")]
+    InvalidCredentials(String),
+    #[error("Username is already taken")]
+    UsernameTaken,
+    #[error("Incorrect username or password")]
+    WrongPassword,
+    #[error("Something went wrong, try again")]
+    Internal,
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            AuthError::InvalidCredentials(_) => StatusCode::BAD_REQUEST,
+            AuthError::UsernameTaken => StatusCode::CONFLICT,
+            AuthError::WrongPassword => StatusCode::UNAUTHORIZED,
+            AuthError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(serde_json::json!({ "message": self.to_string() }))).into_response()
+    }
+}
+
+impl Credentials {
+    fn validate(&self) -> Result<(), AuthError> {
+        let username_length = self.username.chars().count();
+        if !(MIN_USERNAME_LENGTH..=MAX_USERNAME_LENGTH).contains(&username_length)
+            || !self.username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(AuthError::InvalidCredentials(format!(
+                "Username should be {MIN_USERNAME_LENGTH} to {MAX_USERNAME_LENGTH} letters, digits or underscores"
+            )));
+        }
+        let password_length = self.password.chars().count();
+        if !(MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH).contains(&password_length) {
+            return Err(AuthError::InvalidCredentials(format!(
+                "Password should be {MIN_PASSWORD_LENGTH} to {MAX_PASSWORD_LENGTH} characters long"
+            )));
+        }
+        Ok(())
+    }
+}
+
+pub async fn register(
+    State(state): State<AuthState>,
+    Json(credentials): Json<Credentials>,
+) -> Result<(StatusCode, Json<AuthResponse>), AuthError> {
+    credentials.validate()?;
+    let password_hash = session::hash_password(credentials.password)
+        .await
+        .ok_or(AuthError::Internal)?;
+    let account = AccountRecord {
+        user_id: Uuid::new_v4().to_string(),
+        username: credentials.username,
+        password_hash,
+        created_at: storage::now_secs(),
+    };
+
+    let created = state.storage.create_account(account.clone()).await.map_err(|err| {
+        error!("Failed to create account: {err}");
+        AuthError::Internal
+    })?;
+    if !created {
+        return Err(AuthError::UsernameTaken);
+    }
+    info!("Registered account {}", account.username);
+    Ok((StatusCode::CREATED, Json(state.auth_response(account))))
+}
+
+pub async fn login(
+    State(state): State<AuthState>,
+    Json(credentials): Json<Credentials>,
+) -> Result<Json<AuthResponse>, AuthError> {
+    let account = state.storage.load_account(&credentials.username).await.map_err(|err| {
+        error!("Failed to load account: {err}");
+        AuthError::Internal
+    })?;
+    let Some(account) = account else {
+        let dummy_hash = DUMMY_PASSWORD_HASH
+            .get_or_init(|| session::hash_password("not a real password".to_string()))
+            .await;
+        if let Some(hash) = dummy_hash {
+            session::verify_password(credentials.password, hash.clone()).await;
+        }
+        return Err(AuthError::WrongPassword);
+    };
+    if !session::verify_password(credentials.password, account.password_hash.clone()).await {
+        return Err(AuthError::WrongPassword);
+    }
+    Ok(Json(state.auth_response(account)))
+}
+
+impl AuthState {
+    fn auth_response(&self, account: AccountRecord) -> AuthResponse {
+        AuthResponse {
+            token: self.user_tokens.issue(&account.user_id),
+            user_id: account.user_id,
+            username: account.username,
+        }
+    }
+}
+
+/// Reads an `Authorization: Bearer` header, browsers can't set one on a websocket so a `token` query
+/// parameter is accepted as well.
+pub fn bearer_token<'a>(headers: &'a HeaderMap, query_token: Option<&'a str>) -> Option<&'a str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or(query_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::TokenSigner;
+    use crate::storage::MemoryStorage;
+    use std::{sync::Arc, time::Duration};
+
+    fn auth_state() -> AuthState {
+        AuthState {
+            storage: Arc::new(MemoryStorage::new()),
+            user_tokens: UserTokens::new(TokenSigner::new(None), Duration::from_secs(60)),
+        }
+    }
+
+    fn credentials(username: &str, password: &str) -> Json<Credentials> {
+        Json(Credentials {
+            username: username.to_string(),
+            password: password.to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn usernames_can_only_be_registered_once() {
+        let state = auth_state();
+        assert!(
+            register(State(state.clone()), credentials("player", "password1"))
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            register(State(state), credentials("player", "password2")).await,
+            Err(AuthError::UsernameTaken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn wrong_passwords_and_unknown_users_are_rejected() {
+        let state = auth_state();
+        assert!(
+            register(State(state.clone()), credentials("player", "password1"))
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            login(State(state.clone()), credentials("player", "password2")).await,
+            Err(AuthError::WrongPassword)
+        ));
+        assert!(matches!(
+            login(State(state), credentials("stranger", "password1")).await,
+            Err(AuthError::WrongPassword)
+        ));
+    }
+
+    #[tokio::test]
+    async fn issued_tokens_resolve_to_the_account() {
+        let state = auth_state();
+        let (_, Json(registered)) = register(State(state.clone()), credentials("player", "password1"))
+            .await
+            .unwrap();
+        assert_eq!(
+            state.user_tokens.verify(&registered.token).as_ref(),
+            Some(&registered.user_id)
+        );
+
+        let Json(logged_in) = login(State(state.clone()), credentials("player", "password1"))
+            .await
+            .unwrap();
+        assert_eq!(logged_in.user_id, registered.user_id);
+        assert_eq!(
+            state.user_tokens.verify(&logged_in.token).as_ref(),
+            Some(&registered.user_id)
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use crate::game::{GameError, PlayerHandle};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt::Debug, sync::Arc};
+use ts_rs::TS;
+
+pub const MAX_CHAT_LENGTH: usize = 200;
+pub const MAX_CHAT_HISTORY: usize = 50;
+
+/// Slurs and strong profanity only, everyday words like "kill" or "die" have to get through in a word game.
+const BANNED_WORDS: &str = include_str!("../chat-banned-words.txt");
+
+pub type SharedChatFilter = Arc<dyn ChatFilter>;
+
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatEntry {
+    pub from: PlayerHandle,
+    pub text: String,
+    /// Unix time in milliseconds.
+    #[ts(type = "number")]
+    pub ts: u64,
+}
+
+/// Moderation hook every chat message passes through before it is relayed.
+///
+/// Filters only ever see the message, never the round's solution, so nothing they block or rewrite can be used
+/// to probe for it.
+pub trait ChatFilter: Send + Sync + Debug {
+    /// Returns the text to relay, or an error to bounce back to the sender.
+    fn filter(&self, text: &str) -> Result<String, GameError>;
+}
+
+/// Masks whole words found in a banned list.
+#[derive(Debug)]
+pub struct BannedWordsFilter {
+    banned: HashSet<String>,
+}
+
+impl BannedWordsFilter {
+    pub fn new(words: &str) -> Self {
+        let banned = words
+            .lines()
+            .map(|line| line.trim().to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+        BannedWordsFilter { banned }
+    }
+}
+
+impl Default for BannedWordsFilter {
+    fn default() -> Self {
+        Self::new(BANNED_WORDS)
+    }
+}
+
+impl ChatFilter for BannedWordsFilter {
+    fn filter(&self, text: &str) -> Result<String, GameError> {
+        let mut filtered = String::with_capacity(text.len());
+        let mut word = String::new();
+        for c in text.chars().chain(std::iter::once(' ')) {
+            if c.is_alphanumeric() {
+                word.push(c);
+                continue;
+            }
+            if self.banned.contains(&word.to_lowercase()) {
+                filtered.extend(std::iter::repeat_n('*', word.chars().count()));
+            } else {
+                filtered.push_str(&word);
+            }
+            word.clear();
+            filtered.push(c);
+        }
+        filtered.pop();
+        Ok(filtered)
+    }
+}
+
+/// Trims the message and enforces the length limits, whatever filter is in use.
+pub fn validate(text: &str) -> Result<&str, GameError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(GameError::ChatError {
+            message: "Message is empty".to_string(),
+        });
+    }
+    if text.chars().count() > MAX_CHAT_LENGTH {
+        return Err(GameError::ChatError {
+            message: format!("Messages can be at most {MAX_CHAT_LENGTH} characters long"),
+        });
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn banned_words_are_masked() {
+        let filter = BannedWordsFilter::default();
+        assert_eq!(filter.filter("well SHIT, again").unwrap(), "well ****, again");
+        assert_eq!(filter.filter("shitake").unwrap(), "shitake");
+    }
+
+    #[test]
+    fn everyday_words_get_through() {
+        let filter = BannedWordsFilter::default();
+        let text = "kill it or die trying, hell of a guess";
+        assert_eq!(filter.filter(text).unwrap(), text);
+    }
+
+    #[test]
+    fn blank_and_long_messages_are_rejected() {
+        assert!(matches!(validate("   "), Err(GameError::ChatError { .. })));
+        assert!(matches!(
+            validate(&"a".repeat(MAX_CHAT_LENGTH + 1)),
+            Err(GameError::ChatError { .. })
+        ));
+        assert_eq!(validate("  hi  ").unwrap(), "hi");
+    }
+}
@@ -6,7 +6,15 @@ use crate::game::GameId;
 
 const ALLOWED_GUESS_WORDS: &str = include_str!("../wordle-allowed-guesses.txt");
 const ALLOWED_SOLUTION_WORDS: &str = include_str!("../wordle-answers-alphabetical.txt");
-const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const GAME_ID_BLOCKLIST: &str = include_str!("../game-id-blocklist.txt");
+/// Characters that are easy to misread or mistype when a room code is shared out loud.
+const AMBIGUOUS_CHARACTERS: &[u8] = b"01ILO";
+/// Draws allowed before giving up on finding an id that doesn't spell a blocked word.
+const MAX_BLOCKED_ID_DRAWS: usize = 64;
+
+pub const WORD_LENGTH: usize = 5;
+pub const DEFAULT_GAME_ID_LENGTH: usize = 6;
+pub const DEFAULT_GAME_ID_ALPHABET: &str = "ABCDEFGHJKMNPQRSTUVWXYZ23456789";
 
 pub static VALID_GUESS_WORDS: Lazy<HashSet<String>> = Lazy::new(|| {
     let mut set = HashSet::new();
@@ -30,6 +38,14 @@ pub static VALID_SOLUTION_WORDS: Lazy<Vec<String>> = Lazy::new(|| {
         .collect()
 });
 
+static BLOCKED_GAME_IDS: Lazy<Vec<String>> = Lazy::new(|| {
+    GAME_ID_BLOCKLIST
+        .lines()
+        .map(|line| line.trim().to_uppercase())
+        .filter(|word| !word.is_empty())
+        .collect()
+});
+
 pub fn valid_guess(guess: &str) -> bool {
     VALID_GUESS_WORDS.contains(&guess.to_uppercase()) || VALID_SOLUTION_WORDS.contains(&guess.to_uppercase())
 }
@@ -44,11 +60,100 @@ pub fn random_solution() -> String {
         .unwrap_or_else(|| panic!("Solution word vec is empty"))
 }
 
-pub fn random_game_id() -> GameId {
-    let mut rng = rng();
-    let game_id: Vec<u8> = (0..5).map(|_| {
-        *ALPHABET.choose(&mut rng).unwrap()
-    }).collect();
+/// Everyone playing the daily puzzle on `day`, counted in days since the epoch, gets this word.
+pub fn daily_solution(day: u64) -> String {
+    let solutions = VALID_SOLUTION_WORDS.as_slice();
+    // Stepping by a large prime spreads consecutive days across the alphabetical list.
+    let index = day.wrapping_mul(7_919) % solutions.len() as u64;
+    solutions[index as usize].clone()
+}
+
+#[derive(Debug, Clone)]
+pub struct GameIdGenerator {
+    length: usize,
+    alphabet: Vec<u8>,
+}
+
+impl GameIdGenerator {
+    /// Returns `None` when there is nothing left to build ids from once ambiguous characters are dropped,
+    /// or when the ids it builds keep spelling blocked words.
+    pub fn new(length: usize, alphabet: &str) -> Option<Self> {
+        let mut alphabet: Vec<u8> = alphabet
+            .to_uppercase()
+            .bytes()
+            .filter(|c| c.is_ascii_alphanumeric() && !AMBIGUOUS_CHARACTERS.contains(c))
+            .collect();
+        alphabet.sort_unstable();
+        alphabet.dedup();
+
+        if length == 0 || alphabet.len() < 2 {
+            return None;
+        }
+        let generator = GameIdGenerator { length, alphabet };
+        generator.generate()?;
+        Some(generator)
+    }
 
-    String::from_utf8(game_id).expect("Failed to make random game if")
+    /// A random id that isn't on the blocklist, `None` if none turned up within [`MAX_BLOCKED_ID_DRAWS`] draws.
+    pub fn generate(&self) -> Option<GameId> {
+        let mut rng = rng();
+        (0..MAX_BLOCKED_ID_DRAWS)
+            .map(|_| {
+                let game_id: Vec<u8> = (0..self.length)
+                    .map(|_| *self.alphabet.choose(&mut rng).unwrap())
+                    .collect();
+                String::from_utf8(game_id).expect("Failed to make random game id")
+            })
+            .find(|game_id| !is_blocked_game_id(game_id))
+    }
+}
+
+/// Catches blocked words hidden behind digits that look like letters, e.g. `5H1T`.
+fn is_blocked_game_id(game_id: &str) -> bool {
+    let normalized: String = game_id
+        .chars()
+        .map(|c| match c {
+            '0' => 'O',
+            '1' => 'I',
+            '2' => 'Z',
+            '3' => 'E',
+            '4' => 'A',
+            '5' => 'S',
+            '6' | '9' => 'G',
+            '7' => 'T',
+            '8' => 'B',
+            c => c,
+        })
+        .collect();
+    BLOCKED_GAME_IDS.iter().any(|word| normalized.contains(word.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocked_words_are_caught_behind_lookalike_digits() {
+        assert!(is_blocked_game_id("X5H1TX"));
+        assert!(is_blocked_game_id("ASSXYZ"));
+        assert!(!is_blocked_game_id("BCDFGH"));
+    }
+
+    #[test]
+    fn generated_ids_skip_blocked_words() {
+        let game_ids = GameIdGenerator::new(3, "ASX").unwrap();
+        for _ in 0..200 {
+            let game_id = game_ids.generate().unwrap();
+            assert_eq!(game_id.len(), 3);
+            assert!(!game_id.contains("ASS"), "{game_id} should have been blocked");
+        }
+    }
+
+    #[test]
+    fn ambiguous_characters_are_dropped_from_the_alphabet() {
+        assert!(GameIdGenerator::new(6, "01ILO").is_none());
+        assert!(GameIdGenerator::new(0, DEFAULT_GAME_ID_ALPHABET).is_none());
+        let game_id = GameIdGenerator::new(8, "AB0").unwrap().generate().unwrap();
+        assert!(game_id.chars().all(|c| c == 'A' || c == 'B'));
+    }
 }
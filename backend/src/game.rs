@@ -1,25 +1,98 @@
+use crate::chat::{self, ChatEntry, MAX_CHAT_HISTORY, SharedChatFilter};
 use crate::dict;
+use crate::leaderboard::{LeaderboardEntry, LeaderboardQuery};
+use crate::matchmaking::{self, MatchQueue, MatchTicket};
+use crate::session;
+use crate::storage::{self, GameRecord, PlayerStats, RoundRecord, SharedStorage};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use tokio::sync::mpsc::{self, Receiver};
-use tracing::{error, instrument, warn};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use tokio::{
+    sync::mpsc::{self, Receiver, error::SendError},
+    task::JoinHandle,
+    time::{Duration, Instant, interval, sleep_until},
+};
+use tracing::{error, info, instrument, warn};
+use ts_rs::TS;
 
 pub type GameId = String;
 pub type PlayerId = String;
+pub type UserId = String;
+pub type PlayerHandle = String;
 pub type PlayerSender = mpsc::Sender<ServerMessage>;
+pub type RequestId = String;
 pub type CommandSender = mpsc::Sender<GameCommand>;
+pub type RoomSender = mpsc::UnboundedSender<RoomEvent>;
 
-const MAX_GUESSES: usize = 6;
+pub const MAX_GUESSES: usize = 6;
+pub const REACTION_EMOJIS: &[&str] = &["👏", "🔥", "😮", "😂", "🤔", "💀"];
+const MAX_PLAYERS: usize = 2;
+const MAX_LISTED_ROOMS: usize = 50;
+const MAX_DISPLAY_NAME_LENGTH: usize = 16;
+const MAX_GAME_ID_ATTEMPTS: usize = 16;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone)]
 #[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
 pub enum GameError {
     StopGame,
-    JoinError { message: String },
-    GuessError { message: String },
+    JoinError {
+        message: String,
+    },
+    /// A missing or wrong room password, connections that send too many can't join rooms for a while.
+    PasswordError {
+        message: String,
+    },
+    GuessError {
+        message: String,
+    },
+    InvalidResumeToken {
+        message: String,
+    },
+    ExpiredResumeToken {
+        message: String,
+    },
+    ProfileError {
+        message: String,
+    },
+    PermissionDenied {
+        message: String,
+    },
+    PlayerNotFound {
+        message: String,
+    },
+    SettingsError {
+        message: String,
+    },
+    ChatError {
+        message: String,
+    },
+    ReactionError {
+        message: String,
+    },
+    UnsupportedProtocol {
+        message: String,
+        min_version: u32,
+        max_version: u32,
+    },
+    RateLimitError {
+        message: String,
+    },
+    InvalidMessage {
+        message: String,
+    },
+    UnknownAction {
+        message: String,
+        action: String,
+    },
+    NotConnected {
+        message: String,
+    },
+    NotInGame {
+        message: String,
+    },
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone)]
 #[serde(tag = "status", rename_all = "camelCase", rename_all_fields = "camelCase")]
 pub enum ServerMessage {
     Created {
@@ -40,17 +113,109 @@ pub enum ServerMessage {
     },
     Welcome {
         player_id: PlayerId,
+        handle: PlayerHandle,
         message: String,
+        /// Long lived identity that ratings and stats are kept under, send it back on the next connect.
+        user_token: String,
+        protocol_version: u32,
+        features: Vec<String>,
+    },
+    ResumeToken {
+        resume_token: String,
     },
     Error {
         error: GameError,
+        /// The `requestId` of the command that failed, when it carried one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        request_id: Option<RequestId>,
+    },
+    /// Confirms a command that carried a `requestId` went through.
+    Ack {
+        request_id: RequestId,
     },
     Exited {
         board_state: BoardState,
     },
+    ServerShuttingDown {
+        #[ts(type = "number")]
+        reconnect_after: u64,
+    },
+    RoomClosed {
+        reason: String,
+        /// Rooms closed for a restart can be joined again once the server is back.
+        resumable: bool,
+    },
+    Kicked {
+        game_id: GameId,
+    },
+    RoomList {
+        rooms: Vec<RoomSummary>,
+    },
+    Stats {
+        user_id: UserId,
+        stats: PlayerStats,
+    },
+    Leaderboard {
+        #[serde(flatten)]
+        query: LeaderboardQuery,
+        entries: Vec<LeaderboardEntry>,
+    },
+    ChatMessage {
+        #[serde(flatten)]
+        entry: ChatEntry,
+    },
+    /// What the player whose turn it is has typed so far, only sent to their teammates.
+    Draft {
+        from: PlayerHandle,
+        letters: String,
+    },
+    /// A word a teammate proposes, only sent to the player whose turn it is.
+    Suggestion {
+        from: PlayerHandle,
+        word: String,
+    },
+    /// Sent instead of a full `GameUpdate` when only part of the board changed.
+    BoardDelta {
+        #[ts(type = "number")]
+        version: u64,
+        changes: Vec<BoardChange>,
+    },
+    /// Sent instead of a full `GameUpdate` when a reaction is added.
+    Reaction {
+        #[ts(type = "number")]
+        version: u64,
+        guess_index: usize,
+        emoji: String,
+        count: u32,
+        from: PlayerHandle,
+    },
+    MatchQueued {
+        position: usize,
+    },
+    MatchCancelled,
+    MatchTimedOut,
+}
+
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum BoardChange {
+    GuessAdded {
+        guess: GuessResult,
+    },
+    /// Only the keys whose color changed.
+    KeysUpdated {
+        keys: HashMap<char, GameColor>,
+    },
+    TurnChanged {
+        current_turn: PlayerHandle,
+    },
+    StatusChanged {
+        game_status: GameStatus,
+        solution: Option<String>,
+    },
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase", rename_all_fields = "camelCase")]
 pub enum GameStatus {
     Waiting,
@@ -59,7 +224,7 @@ pub enum GameStatus {
     Lost,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase", rename_all_fields = "camelCase")]
 pub enum GameColor {
     Gray,
@@ -67,37 +232,210 @@ pub enum GameColor {
     Green,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct GuessResult {
     word: String,
     status: Vec<GameColor>,
+    /// How many players reacted to this row with each emoji.
+    #[serde(default)]
+    reactions: BTreeMap<String, u32>,
 }
 
 impl GuessResult {
     fn new(word: String) -> Self {
         let word_length = word.len();
         GuessResult {
-            word: word,
+            word,
             status: vec![GameColor::Gray; word_length],
+            reactions: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicPlayer {
+    handle: PlayerHandle,
+    display_name: String,
+    avatar_color: Option<String>,
+    #[serde(default)]
+    away: bool,
+}
+
+impl PublicPlayer {
+    pub fn new(
+        handle: PlayerHandle,
+        display_name: Option<String>,
+        avatar_color: Option<String>,
+    ) -> Result<Self, GameError> {
+        let display_name = display_name.map(|name| name.trim().to_string());
+        let display_name = match display_name {
+            None => "Player".to_string(),
+            Some(name) if name.is_empty() || name.chars().count() > MAX_DISPLAY_NAME_LENGTH => {
+                return Err(GameError::ProfileError {
+                    message: format!("Display name should be 1 to {MAX_DISPLAY_NAME_LENGTH} characters long"),
+                });
+            }
+            Some(name)
+                if !name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_')) =>
+            {
+                return Err(GameError::ProfileError {
+                    message: "Display name can only contain letters, numbers, spaces, - and _".to_string(),
+                });
+            }
+            Some(name) => name,
+        };
+
+        if let Some(color) = &avatar_color {
+            let is_hex_color =
+                color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+            if !is_hex_color {
+                return Err(GameError::ProfileError {
+                    message: "Avatar color should look like #RRGGBB".to_string(),
+                });
+            }
+        }
+
+        Ok(PublicPlayer {
+            handle,
+            display_name,
+            avatar_color,
+            away: false,
+        })
+    }
+
+    pub fn handle(&self) -> &str {
+        &self.handle
+    }
+}
+
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum GameMode {
+    #[default]
+    Coop,
+    Competitive,
+    /// Co-op on the word of the day, the same for every room.
+    Daily,
+}
+
+impl GameMode {
+    /// Whether the whole room works on the word together.
+    fn is_cooperative(self) -> bool {
+        matches!(self, GameMode::Coop | GameMode::Daily)
+    }
+}
+
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GameSettings {
+    pub mode: GameMode,
+    pub hard_mode: bool,
+    pub word_length: usize,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            mode: GameMode::Coop,
+            hard_mode: false,
+            word_length: dict::WORD_LENGTH,
+        }
+    }
+}
+
+impl GameSettings {
+    fn pick_solution(&self) -> String {
+        match self.mode {
+            GameMode::Daily => dict::daily_solution(storage::today()),
+            GameMode::Coop | GameMode::Competitive => dict::random_solution(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), GameError> {
+        if self.word_length != dict::WORD_LENGTH {
+            return Err(GameError::SettingsError {
+                message: format!("Only {} letter words are supported", dict::WORD_LENGTH),
+            });
         }
+        Ok(())
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// How a room is set up when it is created.
+#[derive(Debug, Clone, Default)]
+pub struct RoomOptions {
+    pub password_hash: Option<String>,
+    pub public: bool,
+    pub settings: GameSettings,
+}
+
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct BoardState {
+    /// Goes up with every change sent to players, deltas apply on top of the version just before theirs.
+    #[serde(default)]
+    #[ts(type = "number")]
+    version: u64,
     guesses: Vec<GuessResult>,
-    current_turn: PlayerId,
+    current_turn: PlayerHandle,
     game_status: GameStatus,
     keyboard_status: HashMap<char, GameColor>,
-    players: Vec<PlayerId>,
+    players: Vec<PublicPlayer>,
+    #[serde(default)]
+    host: PlayerHandle,
+    #[serde(default)]
+    locked: bool,
+    #[serde(default)]
+    public: bool,
+    #[serde(default)]
+    settings: GameSettings,
+}
+
+/// What the lobby knows about a room, pushed by the game actor whenever it changes.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomSummary {
+    game_id: GameId,
+    player_count: usize,
+    mode: GameMode,
+    word_length: usize,
+    status: GameStatus,
+    has_password: bool,
+    #[serde(skip)]
+    public: bool,
+    #[serde(skip)]
+    locked: bool,
+}
+
+impl RoomSummary {
+    fn is_open(&self) -> bool {
+        self.public && !self.locked && self.player_count < MAX_PLAYERS
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RoomEvent {
+    Updated(RoomSummary),
+    Closed(GameId),
 }
 
 impl BoardState {
-    fn add_player(&mut self, player_id: PlayerId) {
-        if !self.players.contains(&player_id) {
-            self.players.push(player_id);
+    fn has_handle(&self, handle: &str) -> bool {
+        self.players.iter().any(|player| player.handle == handle)
+    }
+
+    fn get_player_mut(&mut self, handle: &str) -> Option<&mut PublicPlayer> {
+        self.players.iter_mut().find(|player| player.handle == handle)
+    }
+
+    fn add_player(&mut self, profile: PublicPlayer) {
+        match self.get_player_mut(&profile.handle) {
+            Some(player) => *player = profile,
+            None => self.players.push(profile),
         }
     }
 
@@ -106,40 +444,168 @@ impl BoardState {
         let players = &self.players;
         let current_player = &self.current_turn;
 
-        if let Some(player_index) = players.iter().position(|id| id == current_player) {
+        if let Some(player_index) = players.iter().position(|player| &player.handle == current_player) {
             let next_index = (player_index + 1) % num_players;
-            let next_player = players[next_index].clone();
+            let next_player = players[next_index].handle.clone();
             self.current_turn = next_player;
         }
     }
 }
 
+/// A room waiting for a free game id before it is spawned.
+#[derive(Debug, Clone)]
+pub enum PendingRoom {
+    Create {
+        player_id: PlayerId,
+        user_id: UserId,
+        profile: PublicPlayer,
+        options: RoomOptions,
+        reply_sender: ReplySender,
+    },
+    Match {
+        host: MatchTicket,
+        guest: MatchTicket,
+    },
+}
+
 #[derive(Clone, Debug)]
 pub enum GameCommand {
     Create {
         player_id: PlayerId,
-        reply_sender: PlayerSender,
+        user_id: UserId,
+        profile: PublicPlayer,
+        options: RoomOptions,
+        reply_sender: ReplySender,
     },
     Join {
         game_id: GameId,
         player_id: PlayerId,
+        user_id: UserId,
+        profile: PublicPlayer,
         old_player_id: Option<PlayerId>,
-        reply_sender: PlayerSender,
+        password: Option<String>,
+        reply_sender: ReplySender,
     },
     New {
         game_id: GameId,
-        reply_sender: PlayerSender,
+        player_id: PlayerId,
+        reply_sender: ReplySender,
     },
     Guess {
         game_id: GameId,
         player_id: PlayerId,
         word: String,
-        reply_sender: PlayerSender,
+        reply_sender: ReplySender,
     },
     Disconnect {
         game_id: GameId,
         player_id: PlayerId,
     },
+    ConnectionLost {
+        game_id: GameId,
+        player_id: PlayerId,
+    },
+    Restore {
+        game_id: GameId,
+        record: Option<GameRecord>,
+        join: Box<GameCommand>,
+    },
+    /// A game id nothing live or stored uses, or `None` when none turned up.
+    Reserved {
+        game_id: Option<GameId>,
+        room: Box<PendingRoom>,
+    },
+    Kick {
+        game_id: GameId,
+        player_id: PlayerId,
+        target: PlayerHandle,
+        reply_sender: ReplySender,
+    },
+    Chat {
+        game_id: GameId,
+        player_id: PlayerId,
+        text: String,
+        reply_sender: ReplySender,
+    },
+    Draft {
+        game_id: GameId,
+        player_id: PlayerId,
+        letters: String,
+    },
+    React {
+        game_id: GameId,
+        player_id: PlayerId,
+        guess_index: usize,
+        emoji: String,
+        reply_sender: ReplySender,
+    },
+    Suggest {
+        game_id: GameId,
+        player_id: PlayerId,
+        word: String,
+        reply_sender: ReplySender,
+    },
+    AcceptSuggestion {
+        game_id: GameId,
+        player_id: PlayerId,
+        from: PlayerHandle,
+        reply_sender: ReplySender,
+    },
+    Lock {
+        game_id: GameId,
+        player_id: PlayerId,
+        locked: bool,
+        reply_sender: ReplySender,
+    },
+    SetPublic {
+        game_id: GameId,
+        player_id: PlayerId,
+        public: bool,
+        reply_sender: ReplySender,
+    },
+    UpdateSettings {
+        game_id: GameId,
+        player_id: PlayerId,
+        settings: GameSettings,
+        reply_sender: ReplySender,
+    },
+    TransferHost {
+        game_id: GameId,
+        player_id: PlayerId,
+        target: PlayerHandle,
+        reply_sender: ReplySender,
+    },
+    Snapshot {
+        game_id: GameId,
+        player_id: PlayerId,
+        reply_sender: ReplySender,
+    },
+    ListRooms {
+        reply_sender: ReplySender,
+    },
+    FindMatch {
+        player_id: PlayerId,
+        user_id: UserId,
+        profile: PublicPlayer,
+        settings: GameSettings,
+        rating: f64,
+        reply_sender: ReplySender,
+    },
+    CancelMatch {
+        player_id: PlayerId,
+    },
+    Shutdown,
+    /// Closes every room once the shutdown drain is over, `done` hears back when they have all stopped.
+    CloseAll {
+        reason: String,
+        done: mpsc::Sender<()>,
+    },
+    /// Rooms closed for a restart keep their record so players can resume them afterwards.
+    Close {
+        game_id: GameId,
+        reason: String,
+        resumable: bool,
+    },
 }
 
 impl GameCommand {
@@ -148,79 +614,284 @@ impl GameCommand {
             &Self::Join { game_id, .. }
             | &Self::Guess { game_id, .. }
             | &Self::New { game_id, .. }
-            | &Self::Disconnect { game_id, .. } => Some(game_id.clone()),
+            | &Self::Disconnect { game_id, .. }
+            | &Self::ConnectionLost { game_id, .. }
+            | &Self::Restore { game_id, .. }
+            | &Self::Close { game_id, .. }
+            | &Self::Kick { game_id, .. }
+            | &Self::Chat { game_id, .. }
+            | &Self::React { game_id, .. }
+            | &Self::Draft { game_id, .. }
+            | &Self::Suggest { game_id, .. }
+            | &Self::AcceptSuggestion { game_id, .. }
+            | &Self::Lock { game_id, .. }
+            | &Self::SetPublic { game_id, .. }
+            | &Self::UpdateSettings { game_id, .. }
+            | &Self::TransferHost { game_id, .. }
+            | &Self::Snapshot { game_id, .. } => Some(game_id.clone()),
             _ => None,
         }
     }
 
-    fn get_reply_sender(&self) -> Option<PlayerSender> {
+    fn get_reply_sender(&self) -> Option<ReplySender> {
         match &self {
             &Self::Join { reply_sender, .. }
             | &Self::Guess { reply_sender, .. }
             | &Self::New { reply_sender, .. }
-            | &Self::Create { reply_sender, .. } => Some(reply_sender.clone()),
+            | &Self::Create { reply_sender, .. }
+            | &Self::FindMatch { reply_sender, .. }
+            | &Self::Kick { reply_sender, .. }
+            | &Self::Chat { reply_sender, .. }
+            | &Self::React { reply_sender, .. }
+            | &Self::Suggest { reply_sender, .. }
+            | &Self::AcceptSuggestion { reply_sender, .. }
+            | &Self::Lock { reply_sender, .. }
+            | &Self::SetPublic { reply_sender, .. }
+            | &Self::UpdateSettings { reply_sender, .. }
+            | &Self::TransferHost { reply_sender, .. }
+            | &Self::Snapshot { reply_sender, .. } => Some(reply_sender.clone()),
             _ => None,
         }
     }
 }
 
+/// A player's channel together with the id of the request it answers, so errors and acks can be matched up.
+#[derive(Debug, Clone)]
+pub struct ReplySender {
+    sender: PlayerSender,
+    request_id: Option<RequestId>,
+}
+
+impl ReplySender {
+    pub fn new(sender: PlayerSender, request_id: Option<RequestId>) -> Self {
+        ReplySender { sender, request_id }
+    }
+
+    /// The player's own channel, for everything sent to them after this request.
+    pub fn player_sender(&self) -> PlayerSender {
+        self.sender.clone()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+
+    pub async fn send(&self, message: ServerMessage) -> Result<(), SendError<ServerMessage>> {
+        self.sender.send(message).await
+    }
+
+    pub async fn error(&self, error: GameError) -> Result<(), SendError<ServerMessage>> {
+        let request_id = self.request_id.clone();
+        self.sender.send(ServerMessage::Error { error, request_id }).await
+    }
+
+    /// Only requests that carried an id are acknowledged.
+    pub async fn ack(&self) -> Result<(), SendError<ServerMessage>> {
+        let Some(request_id) = self.request_id.clone() else {
+            return Ok(());
+        };
+        self.sender.send(ServerMessage::Ack { request_id }).await
+    }
+}
+
+/// What every game actor shares with the coordinator that spawned it.
+#[derive(Debug, Clone)]
+struct GameContext {
+    storage: SharedStorage,
+    reconnect_grace: Duration,
+    rooms: RoomSender,
+    chat_filter: SharedChatFilter,
+}
+
 #[derive(Debug)]
 struct Game {
+    game_id: GameId,
     solution_word: String,
     player_senders: HashMap<PlayerId, PlayerSender>,
+    player_handles: HashMap<PlayerId, PlayerHandle>,
+    player_users: HashMap<PlayerId, UserId>,
+    away_deadlines: HashMap<PlayerId, Instant>,
+    password_hash: Option<String>,
     board_state: BoardState,
+    chat_history: VecDeque<ChatEntry>,
+    /// Who already reacted with what on this round's rows, so each player counts once per emoji.
+    reactions: HashSet<(usize, String, PlayerHandle)>,
+    /// Words teammates proposed for the current turn, by who proposed them.
+    suggestions: HashMap<PlayerHandle, String>,
+    published_room: Option<RoomSummary>,
+    /// The latest save, each one waits for the one before so an older board never lands last.
+    saving: Option<JoinHandle<()>>,
+    resumable: bool,
+    context: GameContext,
     rx: Receiver<GameCommand>,
 }
 
 impl Game {
-    fn new(player_id: PlayerId, sender: PlayerSender) -> (CommandSender, Self) {
-        let solution = dict::random_solution();
+    fn new(
+        game_id: GameId,
+        player_id: PlayerId,
+        user_id: UserId,
+        profile: PublicPlayer,
+        options: RoomOptions,
+        sender: PlayerSender,
+        context: GameContext,
+    ) -> (CommandSender, Self) {
+        let solution = options.settings.pick_solution();
         let (tx, rx) = mpsc::channel::<GameCommand>(32);
 
         let board_state = BoardState {
+            version: 0,
             guesses: Vec::new(),
-            current_turn: player_id.clone(),
+            current_turn: profile.handle.clone(),
             game_status: GameStatus::InProgress,
             keyboard_status: HashMap::new(),
-            players: vec![player_id.clone()],
+            players: Vec::new(),
+            host: profile.handle.clone(),
+            locked: false,
+            public: options.public,
+            settings: options.settings,
         };
         let mut game = Game {
+            game_id,
             solution_word: solution,
             player_senders: HashMap::new(),
-            board_state: board_state,
+            player_handles: HashMap::new(),
+            player_users: HashMap::new(),
+            away_deadlines: HashMap::new(),
+            password_hash: options.password_hash,
+            board_state,
+            chat_history: VecDeque::new(),
+            reactions: HashSet::new(),
+            suggestions: HashMap::new(),
+            published_room: None,
+            saving: None,
+            resumable: false,
+            context,
             rx,
         };
+        game.player_handles.insert(player_id.clone(), profile.handle.clone());
+        game.player_users.insert(player_id.clone(), user_id);
         game.player_senders.insert(player_id, sender);
+        game.board_state.add_player(profile);
+        game.persist();
+        game.publish_room();
+        (tx, game)
+    }
+
+    fn restore(record: GameRecord, context: GameContext) -> (CommandSender, Self) {
+        let (tx, rx) = mpsc::channel::<GameCommand>(32);
+        let board_state = BoardState {
+            players: Vec::new(),
+            ..record.board_state
+        };
+        let game = Game {
+            game_id: record.game_id,
+            solution_word: record.solution_word,
+            player_senders: HashMap::new(),
+            player_handles: HashMap::new(),
+            player_users: HashMap::new(),
+            away_deadlines: HashMap::new(),
+            password_hash: record.password_hash,
+            board_state,
+            chat_history: VecDeque::new(),
+            reactions: HashSet::new(),
+            suggestions: HashMap::new(),
+            published_room: None,
+            saving: None,
+            resumable: false,
+            context,
+            rx,
+        };
         (tx, game)
     }
 
     async fn run(&mut self) {
-        while let Some(cmd) = self.rx.recv().await {
-            if let Err(error) = self.process_command(cmd.clone()).await {
-                if let GameError::StopGame = error {
-                    break;
+        loop {
+            let next_deadline = self.away_deadlines.values().min().copied();
+            let result = tokio::select! {
+                Some(cmd) = self.rx.recv() => self.run_command(cmd).await,
+                _ = sleep_until(next_deadline.unwrap_or_else(Instant::now)), if next_deadline.is_some() => {
+                    self.expire_away_players().await
                 }
-                let error_message = ServerMessage::Error { error };
-                if let Some(reply_sender) = cmd.get_reply_sender() {
-                    if let Err(err) = reply_sender.send(error_message).await {
-                        error!("{err}");
-                    }
+                else => break,
+            };
+            if let Err(GameError::StopGame) = result {
+                if !self.resumable {
+                    self.forget().await;
                 }
+                break;
             }
+            self.publish_room();
+        }
+        let _ = self.context.rooms.send(RoomEvent::Closed(self.game_id.clone()));
+    }
+
+    fn room_summary(&self) -> RoomSummary {
+        let player_count = self.board_state.players.len();
+        let status = if player_count < MAX_PLAYERS {
+            GameStatus::Waiting
+        } else {
+            self.board_state.game_status.clone()
+        };
+        RoomSummary {
+            game_id: self.game_id.clone(),
+            player_count,
+            mode: self.board_state.settings.mode,
+            word_length: self.board_state.settings.word_length,
+            status,
+            has_password: self.password_hash.is_some(),
+            public: self.board_state.public,
+            locked: self.board_state.locked,
+        }
+    }
+
+    fn publish_room(&mut self) {
+        let summary = self.room_summary();
+        if self.published_room.as_ref() == Some(&summary) {
+            return;
+        }
+        if self.context.rooms.send(RoomEvent::Updated(summary.clone())).is_err() {
+            warn!("Game coordinator stopped listening for room updates");
+        }
+        self.published_room = Some(summary);
+    }
+
+    async fn run_command(&mut self, cmd: GameCommand) -> Result<(), GameError> {
+        let reply_sender = cmd.get_reply_sender();
+        let reply = match self.process_command(cmd).await {
+            Err(GameError::StopGame) => return Err(GameError::StopGame),
+            Err(error) => reply_sender.map(|reply_sender| (reply_sender, Some(error))),
+            Ok(()) => reply_sender.map(|reply_sender| (reply_sender, None)),
+        };
+        let result = match reply {
+            Some((reply_sender, Some(error))) => reply_sender.error(error).await,
+            Some((reply_sender, None)) => reply_sender.ack().await,
+            None => Ok(()),
+        };
+        if let Err(err) = result {
+            error!("{err}");
         }
+        Ok(())
     }
 
     fn reset(&mut self) {
-        self.solution_word = dict::random_solution();
-        if self.board_state.guesses.len() % 2 == 0 {
+        self.solution_word = self.board_state.settings.pick_solution();
+        self.reactions.clear();
+        self.suggestions.clear();
+        if self.board_state.guesses.len().is_multiple_of(2) {
             self.board_state.next_turn();
         }
         let new_board_state = BoardState {
+            version: self.board_state.version,
             guesses: Vec::new(),
             current_turn: self.board_state.current_turn.clone(),
             game_status: GameStatus::InProgress,
             keyboard_status: HashMap::new(),
             players: self.board_state.players.clone(),
+            host: self.board_state.host.clone(),
+            locked: self.board_state.locked,
+            public: self.board_state.public,
+            settings: self.board_state.settings.clone(),
         };
         self.board_state = new_board_state;
     }
@@ -238,13 +909,13 @@ impl Game {
                 solution_vec[i] = '*';
             }
         }
-        for j in 0..word_len {
-            if guess_vec[j] == '*' {
+        for (j, letter) in guess_vec.iter_mut().enumerate() {
+            if *letter == '*' {
                 continue;
             }
-            if let Some(pos) = solution_vec.iter().position(|&c| c == guess_vec[j]) {
+            if let Some(pos) = solution_vec.iter().position(|c| c == letter) {
                 guess_result.status[j] = GameColor::Yellow;
-                guess_vec[j] = '*';
+                *letter = '*';
                 solution_vec[pos] = '*';
             }
         }
@@ -252,94 +923,459 @@ impl Game {
     }
 
     fn has_ended(&self) -> bool {
-        match self.board_state.game_status {
-            GameStatus::InProgress => false,
-            _ => true,
-        }
+        !matches!(self.board_state.game_status, GameStatus::InProgress)
     }
 
     fn has_player(&self, player_id: &str) -> bool {
-        self.board_state.players.iter().any(|x| x == player_id)
+        self.player_handles.contains_key(player_id)
     }
 
-    async fn process_command(&mut self, command: GameCommand) -> Result<(), GameError> {
-        match command {
-            GameCommand::Join {
-                game_id,
-                player_id,
-                old_player_id,
-                reply_sender,
-            } => {
-                if let Err(err) = self
-                    .handle_join(player_id, old_player_id, game_id, reply_sender.clone())
-                    .await
-                {
-                    return Err(err);
-                }
-            }
-            GameCommand::New { .. } => {
-                self.handle_new().await;
-            }
-            GameCommand::Guess { player_id, word, .. } => {
-                if let Err(err) = self.handle_guess(&player_id, word).await {
-                    return Err(err);
-                }
-            }
-            GameCommand::Disconnect { player_id, .. } => {
-                if let Err(err) = self.handle_disconnect(&player_id).await {
-                    return Err(err);
-                }
-            }
-            _ => {}
+    fn player_id_for(&self, handle: &str) -> Option<PlayerId> {
+        self.player_handles
+            .iter()
+            .find(|(_, player_handle)| *player_handle == handle)
+            .map(|(player_id, _)| player_id.clone())
+    }
+
+    /// The handle of a player seated in this room, for commands only members may send.
+    fn require_player(&self, player_id: &str) -> Result<PlayerHandle, GameError> {
+        self.player_handles
+            .get(player_id)
+            .cloned()
+            .ok_or_else(|| GameError::NotInGame {
+                message: "You are not in this game".to_string(),
+            })
+    }
+
+    fn require_host(&self, player_id: &str) -> Result<(), GameError> {
+        if self.player_handles.get(player_id) != Some(&self.board_state.host) {
+            return Err(GameError::PermissionDenied {
+                message: "Only the host can do that".to_string(),
+            });
         }
         Ok(())
     }
 
-    async fn handle_join(
-        &mut self,
-        player_id: PlayerId,
-        old_player_id: Option<PlayerId>,
-        game_id: GameId,
-        sender: PlayerSender,
-    ) -> Result<(), GameError> {
-        if let Some(pid) = old_player_id {
-            self.player_senders.remove(&pid);
-            self.board_state.players.retain(|id| id != &pid);
+    /// Everything a word has to pass before it can be guessed.
+    fn check_word(&self, word: &str) -> Result<(), GameError> {
+        if !dict::valid_guess(word) {
+            return Err(GameError::GuessError {
+                message: "Not a valid word".to_string(),
+            });
         }
-
-        if self.player_senders.len() > 1 {
-            return Err(GameError::JoinError {
-                message: "Already two players in this game".to_string(),
+        let word_length = self.board_state.settings.word_length;
+        if word.len() != word_length {
+            return Err(GameError::GuessError {
+                message: format!("Word should be {word_length} letters long"),
             });
         }
-
-        self.player_senders.insert(player_id.clone(), sender.clone());
-        self.board_state.add_player(player_id.clone());
-
-        if !self.has_player(&self.board_state.current_turn) {
-            self.board_state.current_turn = player_id.clone();
+        if self.board_state.settings.hard_mode {
+            self.check_hard_mode(word)?;
         }
+        Ok(())
+    }
+
+    /// Hard mode: every revealed hint has to be used in later guesses.
+    fn check_hard_mode(&self, word: &str) -> Result<(), GameError> {
+        let guess: Vec<char> = word.to_uppercase().chars().collect();
+        for previous in &self.board_state.guesses {
+            let letters = previous.word.to_uppercase();
+            for (i, (letter, color)) in letters.chars().zip(&previous.status).enumerate() {
+                match color {
+                    GameColor::Green if guess.get(i) != Some(&letter) => {
+                        return Err(GameError::GuessError {
+                            message: format!("Letter {} must be {letter}", i + 1),
+                        });
+                    }
+                    GameColor::Yellow if !guess.contains(&letter) => {
+                        return Err(GameError::GuessError {
+                            message: format!("Guess must contain {letter}"),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn mark_away(&mut self, player_id: &str) {
+        let Some(handle) = self.player_handles.get(player_id).cloned() else {
+            return;
+        };
+        if let Some(player) = self.board_state.get_player_mut(&handle) {
+            player.away = true;
+        }
+    }
 
-        let solution_word = match self.board_state.game_status {
+    fn ended_solution(&self) -> Option<String> {
+        match self.board_state.game_status {
             GameStatus::Lost | GameStatus::Won => Some(self.solution_word.clone()),
             _ => None,
+        }
+    }
+
+    fn persist(&mut self) {
+        let storage = self.context.storage.clone();
+        let record = GameRecord {
+            game_id: self.game_id.clone(),
+            solution_word: self.solution_word.clone(),
+            board_state: self.board_state.clone(),
+            password_hash: self.password_hash.clone(),
+            updated_at: storage::now_secs(),
+        };
+        let previous = self.saving.take();
+        self.saving = Some(tokio::spawn(async move {
+            if let Some(previous) = previous {
+                let _ = previous.await;
+            }
+            if let Err(err) = storage.save_game(record).await {
+                error!("Failed to save game: {err}");
+            }
+        }));
+    }
+
+    /// Deletes the stored room once any save still in flight has landed.
+    async fn forget(&mut self) {
+        if let Some(saving) = self.saving.take() {
+            let _ = saving.await;
+        }
+        if let Err(err) = self.context.storage.delete_game(&self.game_id).await {
+            error!("Failed to delete game: {err}");
+        }
+    }
+
+    fn record_round(&self, solver: &str) {
+        let storage = self.context.storage.clone();
+        let won = matches!(self.board_state.game_status, GameStatus::Won);
+        let mode = self.board_state.settings.mode;
+        let solver = self.player_users.get(solver).cloned();
+        // Co-op rounds are won or lost together, in competitive only the solver takes the win.
+        let winners = match (won, mode) {
+            (false, _) => Vec::new(),
+            (true, GameMode::Coop | GameMode::Daily) => self.player_users.values().cloned().collect(),
+            (true, GameMode::Competitive) => solver.iter().cloned().collect(),
+        };
+        let round = RoundRecord {
+            game_id: self.game_id.clone(),
+            solution_word: self.solution_word.clone(),
+            guesses: self
+                .board_state
+                .guesses
+                .iter()
+                .map(|guess| guess.word.clone())
+                .collect(),
+            players: self.player_users.values().cloned().collect(),
+            winners,
+            mode,
+            game_status: self.board_state.game_status.clone(),
+            finished_at: storage::now_secs(),
         };
+        tokio::spawn(async move {
+            for user_id in &round.players {
+                if let Err(err) = storage.record_player_stats(user_id, &round).await {
+                    error!("Failed to record player stats: {err}");
+                }
+            }
+            if mode == GameMode::Competitive
+                && let [first, second] = round.players.as_slice()
+            {
+                // A round nobody solved counts as a draw.
+                let score = match &solver {
+                    Some(solver) if won && solver == first => 1.0,
+                    Some(_) if won => 0.0,
+                    _ => 0.5,
+                };
+                if let Err(err) = matchmaking::update_ratings(&storage, first, second, score).await {
+                    error!("Failed to update ratings: {err}");
+                }
+            }
+            if let Err(err) = storage.append_round(round).await {
+                error!("Failed to append round: {err}");
+            }
+        });
+    }
+
+    async fn process_command(&mut self, command: GameCommand) -> Result<(), GameError> {
+        match command {
+            GameCommand::Join {
+                player_id,
+                user_id,
+                profile,
+                old_player_id,
+                password,
+                reply_sender,
+                ..
+            } => {
+                self.handle_join(
+                    player_id,
+                    user_id,
+                    profile,
+                    old_player_id,
+                    password,
+                    reply_sender.player_sender(),
+                )
+                .await?
+            }
+            GameCommand::New { player_id, .. } => self.handle_new(&player_id).await?,
+            GameCommand::Guess { player_id, word, .. } => self.handle_guess(&player_id, word).await?,
+            GameCommand::Disconnect { player_id, .. } => self.handle_disconnect(&player_id).await?,
+            GameCommand::ConnectionLost { player_id, .. } => self.handle_connection_lost(&player_id).await,
+            GameCommand::Close { reason, resumable, .. } => {
+                self.resumable = resumable;
+                Self::broadcast_message(self, ServerMessage::RoomClosed { reason, resumable }).await;
+                return Err(GameError::StopGame);
+            }
+            GameCommand::Kick { player_id, target, .. } => self.handle_kick(&player_id, &target).await?,
+            GameCommand::Chat { player_id, text, .. } => self.handle_chat(&player_id, &text).await?,
+            GameCommand::Draft { player_id, letters, .. } => self.handle_draft(&player_id, letters).await,
+            GameCommand::React {
+                player_id,
+                guess_index,
+                emoji,
+                ..
+            } => self.handle_react(&player_id, guess_index, emoji).await?,
+            GameCommand::Suggest { player_id, word, .. } => self.handle_suggest(&player_id, word).await?,
+            GameCommand::AcceptSuggestion { player_id, from, .. } => {
+                self.handle_accept_suggestion(&player_id, &from).await?
+            }
+            GameCommand::Lock { player_id, locked, .. } => {
+                self.require_host(&player_id)?;
+                self.board_state.locked = locked;
+                self.broadcast_update().await;
+            }
+            GameCommand::SetPublic { player_id, public, .. } => {
+                self.require_host(&player_id)?;
+                self.board_state.public = public;
+                self.broadcast_update().await;
+            }
+            GameCommand::UpdateSettings {
+                player_id, settings, ..
+            } => self.handle_update_settings(&player_id, settings).await?,
+            GameCommand::TransferHost { player_id, target, .. } => {
+                self.require_host(&player_id)?;
+                if !self.board_state.has_handle(&target) {
+                    return Err(GameError::PlayerNotFound {
+                        message: "No such player in this room".to_string(),
+                    });
+                }
+                self.board_state.host = target;
+                self.broadcast_update().await;
+            }
+            GameCommand::Snapshot {
+                player_id,
+                reply_sender,
+                ..
+            } => {
+                self.require_player(&player_id)?;
+                let snapshot = ServerMessage::GameUpdate {
+                    board_state: self.board_state.clone(),
+                    solution: self.ended_solution(),
+                };
+                if let Err(err) = reply_sender.send(snapshot).await {
+                    error!("{err}");
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_join(
+        &mut self,
+        player_id: PlayerId,
+        user_id: UserId,
+        mut profile: PublicPlayer,
+        old_player_id: Option<PlayerId>,
+        password: Option<String>,
+        sender: PlayerSender,
+    ) -> Result<(), GameError> {
+        if let Some(pid) = old_player_id {
+            if self.player_handles.get(&pid) == Some(&profile.handle) {
+                self.player_senders.remove(&pid);
+                self.away_deadlines.remove(&pid);
+                self.player_users.remove(&pid);
+                self.player_handles.remove(&pid);
+                self.player_handles.insert(player_id.clone(), profile.handle.clone());
+            } else {
+                // The seat has moved on since the token was issued, a replayed token must not take over the handle.
+                profile.handle = session::random_handle();
+            }
+        }
+
+        if !self.has_player(&player_id) && self.board_state.locked {
+            return Err(GameError::JoinError {
+                message: "Room is locked".to_string(),
+            });
+        }
+        if !self.has_player(&player_id) && self.board_state.players.len() >= MAX_PLAYERS {
+            return Err(GameError::JoinError {
+                message: "Already two players in this game".to_string(),
+            });
+        }
+        if !self.has_player(&player_id)
+            && let Some(hash) = &self.password_hash
+        {
+            let Some(password) = password else {
+                return Err(GameError::PasswordError {
+                    message: "This room needs a password".to_string(),
+                });
+            };
+            if !session::verify_password(password, hash.clone()).await {
+                return Err(GameError::PasswordError {
+                    message: "Incorrect room password".to_string(),
+                });
+            }
+        }
+
+        self.player_senders.insert(player_id.clone(), sender.clone());
+        self.player_handles.insert(player_id.clone(), profile.handle.clone());
+        self.player_users.insert(player_id.clone(), user_id);
+        if !self.board_state.has_handle(&self.board_state.current_turn) {
+            self.board_state.current_turn = profile.handle.clone();
+        }
+        if !self.board_state.has_handle(&self.board_state.host) {
+            self.board_state.host = profile.handle.clone();
+        }
+        self.board_state.add_player(profile);
+        self.next_version();
 
         let join_message = ServerMessage::Joined {
             board_state: self.board_state.clone(),
-            game_id: game_id.clone(),
-            solution: solution_word,
+            game_id: self.game_id.clone(),
+            solution: self.ended_solution(),
         };
         Self::broadcast_message(self, join_message).await;
+        for entry in &self.chat_history {
+            let chat_message = ServerMessage::ChatMessage { entry: entry.clone() };
+            if let Err(err) = sender.send(chat_message).await {
+                error!("{err}");
+            }
+        }
+        self.persist();
+        Ok(())
+    }
+
+    /// Drafts that don't apply are dropped quietly, they are only a preview.
+    async fn handle_draft(&self, player_id: &str, letters: String) {
+        let Some(from) = self.player_handles.get(player_id) else {
+            return;
+        };
+        if *from != self.board_state.current_turn
+            || self.board_state.game_status != GameStatus::InProgress
+            || !self.board_state.settings.mode.is_cooperative()
+            || letters.chars().count() > self.board_state.settings.word_length
+            || !letters.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return;
+        }
+
+        let draft = ServerMessage::Draft {
+            from: from.clone(),
+            letters: letters.to_uppercase(),
+        };
+        for (pid, sender) in &self.player_senders {
+            if pid != player_id
+                && let Err(err) = sender.send(draft.clone()).await
+            {
+                error!("{err}");
+            }
+        }
+    }
+
+    async fn handle_react(&mut self, player_id: &str, guess_index: usize, emoji: String) -> Result<(), GameError> {
+        let from = self.require_player(player_id)?;
+        if !REACTION_EMOJIS.contains(&emoji.as_str()) {
+            return Err(GameError::ReactionError {
+                message: "That reaction isn't available".to_string(),
+            });
+        }
+        let Some(guess) = self.board_state.guesses.get_mut(guess_index) else {
+            return Err(GameError::ReactionError {
+                message: "No such guess to react to".to_string(),
+            });
+        };
+        if !self.reactions.insert((guess_index, emoji.clone(), from.clone())) {
+            return Ok(());
+        }
+
+        let count = guess.reactions.entry(emoji.clone()).or_default();
+        *count += 1;
+        let count = *count;
+        let reaction = ServerMessage::Reaction {
+            version: self.next_version(),
+            guess_index,
+            emoji,
+            count,
+            from,
+        };
+        Self::broadcast_message(self, reaction).await;
+        self.persist();
+        Ok(())
+    }
+
+    async fn handle_suggest(&mut self, player_id: &str, word: String) -> Result<(), GameError> {
+        let from = self.require_player(player_id)?;
+        if self.board_state.game_status != GameStatus::InProgress || !self.board_state.settings.mode.is_cooperative() {
+            return Err(GameError::GuessError {
+                message: "Suggestions are only for co-op games in progress".to_string(),
+            });
+        }
+        if from == self.board_state.current_turn {
+            return Err(GameError::GuessError {
+                message: "It's your turn, guess it yourself".to_string(),
+            });
+        }
+        self.check_word(&word)?;
+        let Some(active_id) = self.player_id_for(&self.board_state.current_turn) else {
+            return Ok(());
+        };
+
+        let word = word.to_uppercase();
+        self.suggestions.insert(from.clone(), word.clone());
+        if let Some(sender) = self.player_senders.get(&active_id)
+            && let Err(err) = sender.send(ServerMessage::Suggestion { from, word }).await
+        {
+            error!("{err}");
+        }
+        Ok(())
+    }
+
+    async fn handle_accept_suggestion(&mut self, player_id: &str, from: &str) -> Result<(), GameError> {
+        let Some(word) = self.suggestions.get(from).cloned() else {
+            return Err(GameError::GuessError {
+                message: "That suggestion is no longer available".to_string(),
+            });
+        };
+        self.handle_guess(player_id, word).await
+    }
+
+    async fn handle_chat(&mut self, player_id: &str, text: &str) -> Result<(), GameError> {
+        let from = self.require_player(player_id)?;
+        let text = self.context.chat_filter.filter(chat::validate(text)?)?;
+        let entry = ChatEntry {
+            from,
+            text,
+            ts: storage::now_millis(),
+        };
+        if self.chat_history.len() >= MAX_CHAT_HISTORY {
+            self.chat_history.pop_front();
+        }
+        self.chat_history.push_back(entry.clone());
+        Self::broadcast_message(self, ServerMessage::ChatMessage { entry }).await;
         Ok(())
     }
 
-    async fn handle_new(&mut self) {
+    async fn handle_new(&mut self, player_id: &str) -> Result<(), GameError> {
+        self.require_player(player_id)?;
         self.reset();
+        self.next_version();
+        self.persist();
         let new_message = ServerMessage::NewGame {
             board_state: self.board_state.clone(),
         };
         Self::broadcast_message(self, new_message).await;
+        Ok(())
     }
 
     async fn handle_guess(&mut self, player_id: &str, word: String) -> Result<(), GameError> {
@@ -352,56 +1388,148 @@ impl Game {
             });
         }
 
-        let mut solution = None;
-
-        if self.board_state.current_turn != player_id {
+        if self.player_handles.get(player_id) != Some(&self.board_state.current_turn) {
             return Err(GameError::GuessError {
                 message: "Not your turn to guess".to_string(),
             });
         }
-        if !dict::valid_guess(&word) {
-            return Err(GameError::GuessError {
-                message: "Not a valid word".to_string(),
-            });
-        }
-        if word.len() != 5 {
-            return Err(GameError::GuessError {
-                message: "Word should be 5 letters long".to_string(),
-            });
-        }
+        self.check_word(&word)?;
         let guess = self.check_guess(&word);
         self.board_state.guesses.push(guess.clone());
         let win = guess.status.iter().all(|x| *x == GameColor::Green);
         if win {
             self.board_state.game_status = GameStatus::Won;
-            solution = Some(self.solution_word.clone());
         } else if self.board_state.guesses.len() >= MAX_GUESSES {
             self.board_state.game_status = GameStatus::Lost;
-            solution = Some(self.solution_word.clone());
         }
 
         self.board_state.next_turn();
+        self.suggestions.clear();
+
+        let keys = Self::update_keyboard_status(self, &guess);
+        let mut changes = vec![
+            BoardChange::GuessAdded { guess },
+            BoardChange::KeysUpdated { keys },
+            BoardChange::TurnChanged {
+                current_turn: self.board_state.current_turn.clone(),
+            },
+        ];
+        if self.has_ended() {
+            changes.push(BoardChange::StatusChanged {
+                game_status: self.board_state.game_status.clone(),
+                solution: self.ended_solution(),
+            });
+        }
+        let delta = ServerMessage::BoardDelta {
+            version: self.next_version(),
+            changes,
+        };
+        self.persist();
+        if self.has_ended() {
+            self.record_round(player_id);
+        }
+        Self::broadcast_message(self, delta).await;
+
+        Ok(())
+    }
 
-        Self::update_keyboard_status(self, &guess);
+    async fn handle_kick(&mut self, player_id: &str, target: &str) -> Result<(), GameError> {
+        self.require_host(player_id)?;
+        if self.board_state.host == target {
+            return Err(GameError::PermissionDenied {
+                message: "The host can't kick themselves".to_string(),
+            });
+        }
+        let Some(target_id) = self.player_id_for(target) else {
+            return Err(GameError::PlayerNotFound {
+                message: "No such player in this room".to_string(),
+            });
+        };
+
+        if let Some(sender) = self.player_senders.get(&target_id) {
+            let kicked_message = ServerMessage::Kicked {
+                game_id: self.game_id.clone(),
+            };
+            if let Err(err) = sender.send(kicked_message).await {
+                error!("{err}");
+            }
+        }
+        self.handle_disconnect(&target_id).await
+    }
+
+    async fn handle_update_settings(&mut self, player_id: &str, settings: GameSettings) -> Result<(), GameError> {
+        self.require_host(player_id)?;
+        if !self.has_ended() && !self.board_state.guesses.is_empty() {
+            return Err(GameError::SettingsError {
+                message: "Settings can only be changed between rounds".to_string(),
+            });
+        }
+        settings.validate()?;
+        if self.board_state.guesses.is_empty() && settings.mode != self.board_state.settings.mode {
+            self.solution_word = settings.pick_solution();
+        }
+        self.board_state.settings = settings;
+        self.broadcast_update().await;
+        Ok(())
+    }
 
+    async fn broadcast_update(&mut self) {
+        self.next_version();
+        self.persist();
         let game_update = ServerMessage::GameUpdate {
             board_state: self.board_state.clone(),
-            solution,
+            solution: self.ended_solution(),
         };
         Self::broadcast_message(self, game_update).await;
+    }
+
+    async fn handle_connection_lost(&mut self, player_id: &str) {
+        if self.player_senders.remove(player_id).is_none() {
+            return;
+        }
+        self.away_deadlines
+            .insert(player_id.to_string(), Instant::now() + self.context.reconnect_grace);
+        self.mark_away(player_id);
+        self.next_version();
+
+        let game_update = ServerMessage::GameUpdate {
+            board_state: self.board_state.clone(),
+            solution: self.ended_solution(),
+        };
+        Self::broadcast_message(self, game_update).await;
+    }
 
+    async fn expire_away_players(&mut self) -> Result<(), GameError> {
+        let now = Instant::now();
+        let expired: Vec<PlayerId> = self
+            .away_deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(player_id, _)| player_id.clone())
+            .collect();
+
+        for player_id in expired {
+            self.handle_disconnect(&player_id).await?;
+        }
         Ok(())
     }
 
     async fn handle_disconnect(&mut self, player_id: &str) -> Result<(), GameError> {
-        self.board_state.players.retain(|id| id != player_id);
-        if let None = self.player_senders.remove(player_id) {
+        let Some(handle) = self.player_handles.remove(player_id) else {
             return Ok(());
-        }
+        };
+        self.board_state.players.retain(|player| player.handle != handle);
+        self.away_deadlines.remove(player_id);
+        self.player_senders.remove(player_id);
+        self.player_users.remove(player_id);
 
         if self.board_state.players.is_empty() {
             return Err(GameError::StopGame);
         }
+        if self.board_state.host == handle {
+            self.board_state.host = self.board_state.players[0].handle.clone();
+        }
+        self.next_version();
 
         let game_update = ServerMessage::Exited {
             board_state: BoardState {
@@ -414,10 +1542,11 @@ impl Game {
         Ok(())
     }
 
-    fn update_keyboard_status(&mut self, guess: &GuessResult) {
+    /// Returns the keys whose color changed.
+    fn update_keyboard_status(&mut self, guess: &GuessResult) -> HashMap<char, GameColor> {
+        let mut changed = HashMap::new();
         let guess_chars: Vec<char> = guess.word.to_uppercase().chars().collect();
-        for i in 0..guess_chars.len() {
-            let char_key = guess_chars[i];
+        for (i, &char_key) in guess_chars.iter().enumerate() {
             let guess_color = guess.status[i].clone();
 
             let current_color = self.board_state.keyboard_status.get(&char_key);
@@ -429,14 +1558,22 @@ impl Game {
                 _ => Some(guess_color),
             };
             if let Some(color) = new_color {
-                self.board_state.keyboard_status.insert(char_key, color);
+                self.board_state.keyboard_status.insert(char_key, color.clone());
+                changed.insert(char_key, color);
             }
         }
+        changed
+    }
+
+    /// Every change players see moves the board to a new version, so clients can spot a missed delta.
+    fn next_version(&mut self) -> u64 {
+        self.board_state.version += 1;
+        self.board_state.version
     }
 
     #[instrument(skip(game))]
     async fn broadcast_message(game: &Game, message: ServerMessage) {
-        for (_, sender) in &game.player_senders {
+        for sender in game.player_senders.values() {
             if let Err(err) = sender.send(message.clone()).await {
                 error!("{err}");
             }
@@ -444,111 +1581,1752 @@ impl Game {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct CoordinatorConfig {
+    pub room_idle_ttl: Duration,
+    pub reconnect_grace: Duration,
+    pub match_timeout: Duration,
+    pub game_ids: dict::GameIdGenerator,
+    pub chat_filter: SharedChatFilter,
+}
+
+#[derive(Debug)]
+struct GameHandle {
+    sender: CommandSender,
+    last_activity: Instant,
+}
+
+impl GameHandle {
+    fn new(sender: CommandSender) -> Self {
+        GameHandle {
+            sender,
+            last_activity: Instant::now(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GameCoordinator {
-    games: HashMap<GameId, mpsc::Sender<GameCommand>>,
+    games: HashMap<GameId, GameHandle>,
     player_games: HashMap<PlayerId, GameId>,
+    rooms: HashMap<GameId, RoomSummary>,
+    match_queue: MatchQueue,
+    storage: SharedStorage,
+    config: CoordinatorConfig,
+    shutting_down: bool,
+    tx: mpsc::Sender<GameCommand>,
     rx: mpsc::Receiver<GameCommand>,
+    rooms_tx: RoomSender,
+    rooms_rx: mpsc::UnboundedReceiver<RoomEvent>,
 }
 
 impl GameCoordinator {
-    pub fn new() -> (mpsc::Sender<GameCommand>, Self) {
+    pub fn new(storage: SharedStorage, config: CoordinatorConfig) -> (mpsc::Sender<GameCommand>, Self) {
         let _ = dict::VALID_GUESS_WORDS.len();
         let _ = dict::VALID_SOLUTION_WORDS.len();
 
         let (tx, rx) = mpsc::channel(32);
+        let (rooms_tx, rooms_rx) = mpsc::unbounded_channel();
         let coordinator = GameCoordinator {
             games: HashMap::new(),
             player_games: HashMap::new(),
+            rooms: HashMap::new(),
+            match_queue: MatchQueue::new(config.match_timeout),
+            storage,
+            config,
+            shutting_down: false,
+            tx: tx.clone(),
             rx,
+            rooms_tx,
+            rooms_rx,
         };
         (tx, coordinator)
     }
 
     #[instrument(skip(self))]
     pub async fn run(mut self) {
-        while let Some(cmd) = self.rx.recv().await {
-            match &cmd {
-                GameCommand::Create {
-                    player_id,
-                    reply_sender,
-                } => {
-                    self.handle_creating_game(&player_id, reply_sender.clone()).await;
-                    continue;
+        let mut reap_interval = interval(
+            self.config
+                .room_idle_ttl
+                .clamp(Duration::from_secs(1), Duration::from_secs(60)),
+        );
+        let mut widen_interval = interval(Duration::from_secs(1));
+
+        loop {
+            let match_deadline = self.match_queue.next_deadline();
+            tokio::select! {
+                Some(cmd) = self.rx.recv() => self.handle_command(cmd).await,
+                Some(event) = self.rooms_rx.recv() => self.handle_room_event(event),
+                _ = reap_interval.tick() => self.reap_idle_games().await,
+                _ = widen_interval.tick(), if !self.match_queue.is_empty() => self.match_widened_tickets().await,
+                _ = sleep_until(match_deadline.unwrap_or_else(Instant::now)), if match_deadline.is_some() => {
+                    self.expire_match_tickets().await
+                }
+                else => break,
+            }
+        }
+    }
+
+    async fn handle_command(&mut self, cmd: GameCommand) {
+        match &cmd {
+            GameCommand::Create {
+                player_id,
+                user_id,
+                profile,
+                options,
+                reply_sender,
+            } => {
+                self.leave_match_queue(player_id).await;
+                let room = PendingRoom::Create {
+                    player_id: player_id.clone(),
+                    user_id: user_id.clone(),
+                    profile: profile.clone(),
+                    options: options.clone(),
+                    reply_sender: reply_sender.clone(),
+                };
+                self.reserve_game_id(room);
+                return;
+            }
+            GameCommand::FindMatch {
+                player_id,
+                user_id,
+                profile,
+                settings,
+                rating,
+                reply_sender,
+            } => {
+                let ticket = MatchTicket {
+                    player_id: player_id.clone(),
+                    user_id: user_id.clone(),
+                    profile: profile.clone(),
+                    settings: settings.clone(),
+                    rating: *rating,
+                    reply_sender: reply_sender.clone(),
+                    queued_at: Instant::now(),
+                };
+                self.handle_find_match(ticket).await;
+                return;
+            }
+            GameCommand::CancelMatch { player_id } => {
+                if let Some(ticket) = self.match_queue.remove(player_id) {
+                    if let Err(err) = ticket.reply_sender.send(ServerMessage::MatchCancelled).await {
+                        error!("{err}");
+                    }
+                    self.send_queue_positions().await;
                 }
-                GameCommand::Join { player_id, game_id, .. } => {
-                    self.disconnect_from_game(player_id).await;
-                    self.player_games.insert(player_id.clone(), game_id.clone());
+                return;
+            }
+            GameCommand::ListRooms { reply_sender } => {
+                self.handle_list_rooms(reply_sender).await;
+                if let Err(err) = reply_sender.ack().await {
+                    error!("{err}");
                 }
-                GameCommand::Disconnect { player_id, .. } => {
-                    self.disconnect_from_game(player_id).await;
+                return;
+            }
+            GameCommand::Join {
+                player_id,
+                game_id,
+                old_player_id,
+                ..
+            } => {
+                self.leave_match_queue(player_id).await;
+                self.disconnect_from_game(player_id).await;
+                if let Some(old_player_id) = old_player_id {
+                    self.player_games.remove(old_player_id);
                 }
-                _ => {}
+                self.player_games.insert(player_id.clone(), game_id.clone());
+            }
+            GameCommand::Disconnect { player_id, .. } => {
+                self.disconnect_from_game(player_id).await;
+            }
+            GameCommand::ConnectionLost { player_id, .. } => {
+                self.player_games.remove(player_id);
             }
-                self.relay_command(cmd).await;
+            GameCommand::Restore { .. } => {
+                self.handle_restore(cmd).await;
+                return;
+            }
+            GameCommand::Reserved { .. } => {
+                self.handle_reserved(cmd).await;
+                return;
+            }
+            GameCommand::Shutdown => {
+                info!("No longer accepting new games");
+                self.shutting_down = true;
+                return;
+            }
+            GameCommand::CloseAll { reason, done } => {
+                self.close_all_games(reason).await;
+                let _ = done.send(()).await;
+                return;
+            }
+            _ => {}
         }
+        self.relay_command(cmd).await;
     }
 
-    async fn relay_command(&mut self, command: GameCommand) {
-        let Some(gid) = command.get_game_id() else {return};
-
-        if let Some(sender) = self.games.get(&gid) {
-            if let Err(err) = sender.send(command).await {
-                warn!("Game actor is dead: {err}");
-                self.games.remove(&gid);
+    fn handle_room_event(&mut self, event: RoomEvent) {
+        match event {
+            RoomEvent::Updated(summary) => {
+                if self.games.contains_key(&summary.game_id) {
+                    self.rooms.insert(summary.game_id.clone(), summary);
+                }
             }
-            return;
-        }
-        if let Some(reply_sender) = command.get_reply_sender() {
-            let error_message = ServerMessage::Error {
-                error: GameError::JoinError {
-                    message: "Game doesnt exist".to_string(),
-                },
-            };
-            if let Err(err) = reply_sender.send(error_message).await {
-                error!("{err}");
+            RoomEvent::Closed(game_id) => {
+                self.rooms.remove(&game_id);
             }
         }
     }
 
-    async fn handle_creating_game(&mut self, player_id: &str, player_sender: PlayerSender) {
-        let (sender, mut game) = Game::new(player_id.to_string(), player_sender.clone());
+    async fn handle_list_rooms(&self, reply_sender: &ReplySender) {
+        let mut rooms: Vec<RoomSummary> = if self.shutting_down {
+            Vec::new()
+        } else {
+            self.rooms.values().filter(|room| room.is_open()).cloned().collect()
+        };
+        rooms.sort_by(|a, b| a.game_id.cmp(&b.game_id));
+        rooms.truncate(MAX_LISTED_ROOMS);
 
-        self.disconnect_from_game(player_id).await;
-        let game_id = dict::random_game_id();
+        if let Err(err) = reply_sender.send(ServerMessage::RoomList { rooms }).await {
+            error!("{err}");
+        }
+    }
 
-        self.add_game(game_id.clone(), player_id.to_string(), sender);
+    fn game_context(&self) -> GameContext {
+        GameContext {
+            storage: self.storage.clone(),
+            reconnect_grace: self.config.reconnect_grace,
+            rooms: self.rooms_tx.clone(),
+            chat_filter: self.config.chat_filter.clone(),
+        }
+    }
 
-        tokio::spawn(async move {
-            game.run().await;
-        });
+    async fn reap_idle_games(&mut self) {
+        let idle_games: Vec<GameId> = self
+            .games
+            .iter()
+            .filter(|(_, handle)| {
+                handle.sender.is_closed() || handle.last_activity.elapsed() >= self.config.room_idle_ttl
+            })
+            .map(|(game_id, _)| game_id.clone())
+            .collect();
+
+        for game_id in idle_games {
+            self.rooms.remove(&game_id);
+            let Some(handle) = self.games.remove(&game_id) else {
+                continue;
+            };
+            info!("Closing idle game {game_id}");
+            let close_message = GameCommand::Close {
+                game_id: game_id.clone(),
+                reason: "Room closed after being idle for too long".to_string(),
+                resumable: false,
+            };
+            if let Err(err) = handle.sender.send(close_message).await {
+                warn!("Game actor is dead: {err}");
+            }
+            self.player_games.retain(|_, gid| gid != &game_id);
+        }
+    }
+
+    async fn close_all_games(&mut self, reason: &str) {
+        let mut closing = Vec::new();
+        for (game_id, handle) in self.games.drain() {
+            let close_message = GameCommand::Close {
+                game_id,
+                reason: reason.to_string(),
+                resumable: true,
+            };
+            if handle.sender.send(close_message).await.is_ok() {
+                closing.push(handle.sender);
+            }
+        }
+        self.player_games.clear();
+        for sender in closing {
+            sender.closed().await;
+        }
+    }
+
+    async fn relay_command(&mut self, command: GameCommand) {
+        let Some(gid) = command.get_game_id() else { return };
+
+        let command = match self.games.get_mut(&gid) {
+            Some(handle) => {
+                handle.last_activity = Instant::now();
+                // Rooms that stopped on their own are only noticed here, the command is then handled as for any unknown room.
+                match handle.sender.send(command).await {
+                    Ok(()) => return,
+                    Err(SendError(command)) => {
+                        warn!("Game actor for {gid} is dead");
+                        self.games.remove(&gid);
+                        self.rooms.remove(&gid);
+                        command
+                    }
+                }
+            }
+            None => command,
+        };
+        if let GameCommand::Join { .. } = command {
+            self.load_game(gid, command);
+            return;
+        }
+        Self::send_missing_game(&command).await;
+    }
+
+    fn load_game(&self, game_id: GameId, join: GameCommand) {
+        let storage = self.storage.clone();
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let record = match storage.load_game(&game_id).await {
+                Ok(record) => record,
+                Err(err) => {
+                    error!("Failed to load game {game_id}: {err}");
+                    None
+                }
+            };
+            let restore = GameCommand::Restore {
+                game_id,
+                record,
+                join: Box::new(join),
+            };
+            if let Err(err) = tx.send(restore).await {
+                error!("Unable to send message to game coordinator {err}");
+            }
+        });
+    }
+
+    async fn handle_restore(&mut self, command: GameCommand) {
+        let GameCommand::Restore { game_id, record, join } = command else {
+            return;
+        };
+        if !self.games.contains_key(&game_id) {
+            let Some(record) = record else {
+                Self::send_missing_game(&join).await;
+                return;
+            };
+            let (sender, mut game) = Game::restore(record, self.game_context());
+            self.games.insert(game_id, GameHandle::new(sender));
+            tokio::spawn(async move {
+                game.run().await;
+            });
+        }
+        self.relay_command(*join).await;
+    }
+
+    async fn send_missing_game(command: &GameCommand) {
+        if let Some(reply_sender) = command.get_reply_sender() {
+            let error = GameError::JoinError {
+                message: "Game doesnt exist".to_string(),
+            };
+            if let Err(err) = reply_sender.error(error).await {
+                error!("{err}");
+            }
+        }
+    }
+
+    /// Looks for an id off the coordinator loop, stored rooms can still be restored by theirs.
+    fn reserve_game_id(&self, room: PendingRoom) {
+        let live: HashSet<GameId> = self.games.keys().cloned().collect();
+        let game_ids = self.config.game_ids.clone();
+        let storage = self.storage.clone();
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let mut game_id = None;
+            for candidate in (0..MAX_GAME_ID_ATTEMPTS).filter_map(|_| game_ids.generate()) {
+                if live.contains(&candidate) {
+                    continue;
+                }
+                match storage.load_game(&candidate).await {
+                    Ok(None) => {
+                        game_id = Some(candidate);
+                        break;
+                    }
+                    Ok(Some(_)) => {}
+                    Err(err) => error!("Failed to check game id {candidate}: {err}"),
+                }
+            }
+            let reserved = GameCommand::Reserved {
+                game_id,
+                room: Box::new(room),
+            };
+            if let Err(err) = tx.send(reserved).await {
+                error!("Unable to send message to game coordinator {err}");
+            }
+        });
+    }
+
+    async fn handle_reserved(&mut self, command: GameCommand) {
+        let GameCommand::Reserved { game_id, room } = command else {
+            return;
+        };
+        // Another room may have spawned with the same id while this one was being checked.
+        if game_id.as_ref().is_some_and(|game_id| self.games.contains_key(game_id)) {
+            self.reserve_game_id(*room);
+            return;
+        }
+        match *room {
+            PendingRoom::Create {
+                player_id,
+                user_id,
+                profile,
+                options,
+                reply_sender,
+            } => {
+                self.handle_creating_game(game_id, &player_id, &user_id, profile, options, reply_sender)
+                    .await
+            }
+            PendingRoom::Match { host, guest } => self.start_match(game_id, host, guest).await,
+        }
+    }
+
+    async fn handle_creating_game(
+        &mut self,
+        game_id: Option<GameId>,
+        player_id: &str,
+        user_id: &str,
+        profile: PublicPlayer,
+        options: RoomOptions,
+        reply_sender: ReplySender,
+    ) {
+        let game_id = match self
+            .spawn_game(
+                game_id,
+                player_id,
+                user_id,
+                profile,
+                options,
+                reply_sender.player_sender(),
+            )
+            .await
+        {
+            Ok(game_id) => game_id,
+            Err(error) => {
+                if let Err(err) = reply_sender.error(error).await {
+                    error!("{err}");
+                }
+                return;
+            }
+        };
 
         let create_message = ServerMessage::Created {
             game_status: GameStatus::Waiting,
-            game_id: game_id,
+            game_id,
         };
-        if let Err(err) = player_sender.send(create_message).await {
+        if let Err(err) = reply_sender.send(create_message).await {
             error!("Failed to send created message to new player: {err}");
+        }
+        if let Err(err) = reply_sender.ack().await {
+            error!("{err}");
+        }
+    }
+
+    async fn spawn_game(
+        &mut self,
+        game_id: Option<GameId>,
+        player_id: &str,
+        user_id: &str,
+        profile: PublicPlayer,
+        options: RoomOptions,
+        player_sender: PlayerSender,
+    ) -> Result<GameId, GameError> {
+        if self.shutting_down {
+            return Err(GameError::JoinError {
+                message: "Server is shutting down, try again shortly".to_string(),
+            });
+        }
+        let Some(game_id) = game_id else {
+            error!("Unable to find a free game id after {MAX_GAME_ID_ATTEMPTS} attempts");
+            return Err(GameError::JoinError {
+                message: "Unable to create a room, try again".to_string(),
+            });
+        };
+        self.disconnect_from_game(player_id).await;
+
+        let (sender, mut game) = Game::new(
+            game_id.clone(),
+            player_id.to_string(),
+            user_id.to_string(),
+            profile,
+            options,
+            player_sender,
+            self.game_context(),
+        );
+        self.add_game(game_id.clone(), player_id.to_string(), sender);
+
+        tokio::spawn(async move {
+            game.run().await;
+        });
+        Ok(game_id)
+    }
+
+    async fn handle_find_match(&mut self, ticket: MatchTicket) {
+        if self.shutting_down {
+            let error = GameError::JoinError {
+                message: "Server is shutting down, try again shortly".to_string(),
+            };
+            if let Err(err) = ticket.reply_sender.error(error).await {
+                error!("{err}");
+            }
+            return;
+        }
+        if let Err(err) = ticket.reply_sender.ack().await {
+            error!("{err}");
+        }
+
+        let Some((host, guest)) = self.match_queue.enqueue(ticket) else {
+            self.send_queue_positions().await;
             return;
+        };
+        self.send_queue_positions().await;
+        self.reserve_game_id(PendingRoom::Match { host, guest });
+    }
+
+    async fn match_widened_tickets(&mut self) {
+        let mut matched = false;
+        while let Some((host, guest)) = self.match_queue.take_widened_pair() {
+            matched = true;
+            self.reserve_game_id(PendingRoom::Match { host, guest });
+        }
+        if matched {
+            self.send_queue_positions().await;
         }
     }
 
-    async fn disconnect_from_game(&mut self, player_id: &str) {
-        if let Some(game_id) = self.player_games.remove(player_id) {
-            if let Some(sender) = self.games.get(&game_id) {
-                let disconnect_message = GameCommand::Disconnect {
-                    player_id: player_id.to_string(),
-                    game_id: game_id.clone(),
-                };
-                if let Err(_) = sender.send(disconnect_message).await {
-                    self.games.remove(&game_id);
+    async fn start_match(&mut self, game_id: Option<GameId>, host: MatchTicket, guest: MatchTicket) {
+        // The guest may have dropped while the room id was being found, the host then waits for someone else.
+        if guest.reply_sender.is_closed() {
+            info!("Match guest {} left before the room was ready", guest.player_id);
+            self.handle_find_match(host).await;
+            return;
+        }
+        let options = RoomOptions {
+            settings: host.settings.clone(),
+            ..RoomOptions::default()
+        };
+        let game_id = match self
+            .spawn_game(
+                game_id,
+                &host.player_id,
+                &host.user_id,
+                host.profile,
+                options,
+                host.reply_sender.player_sender(),
+            )
+            .await
+        {
+            Ok(game_id) => game_id,
+            Err(error) => {
+                for reply_sender in [&host.reply_sender, &guest.reply_sender] {
+                    if let Err(err) = reply_sender.error(error.clone()).await {
+                        error!("{err}");
+                    }
                 }
+                return;
+            }
+        };
+        info!("Matched {} with {} in {game_id}", host.player_id, guest.player_id);
+
+        self.disconnect_from_game(&guest.player_id).await;
+        self.player_games.insert(guest.player_id.clone(), game_id.clone());
+        let join = GameCommand::Join {
+            game_id,
+            player_id: guest.player_id,
+            user_id: guest.user_id,
+            profile: guest.profile,
+            old_player_id: None,
+            password: None,
+            // The guest's find request was acknowledged when it was queued.
+            reply_sender: ReplySender::new(guest.reply_sender.player_sender(), None),
+        };
+        self.relay_command(join).await;
+    }
+
+    async fn leave_match_queue(&mut self, player_id: &str) {
+        if self.match_queue.remove(player_id).is_some() {
+            self.send_queue_positions().await;
+        }
+    }
+
+    async fn expire_match_tickets(&mut self) {
+        for ticket in self.match_queue.expire() {
+            if let Err(err) = ticket.reply_sender.send(ServerMessage::MatchTimedOut).await {
+                error!("{err}");
+            }
+        }
+        self.send_queue_positions().await;
+    }
+
+    async fn send_queue_positions(&self) {
+        for (reply_sender, position) in self.match_queue.positions() {
+            if let Err(err) = reply_sender.send(ServerMessage::MatchQueued { position }).await {
+                error!("{err}");
+            }
+        }
+    }
+
+    async fn disconnect_from_game(&mut self, player_id: &str) {
+        if let Some(game_id) = self.player_games.remove(player_id)
+            && let Some(handle) = self.games.get(&game_id)
+        {
+            let disconnect_message = GameCommand::Disconnect {
+                player_id: player_id.to_string(),
+                game_id: game_id.clone(),
+            };
+            if handle.sender.send(disconnect_message).await.is_err() {
+                self.games.remove(&game_id);
             }
         }
     }
 
     fn add_game(&mut self, game_id: GameId, player_id: PlayerId, sender: CommandSender) {
-        self.games.insert(game_id.clone(), sender);
+        self.games.insert(game_id.clone(), GameHandle::new(sender));
         self.player_games.insert(player_id, game_id);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::BannedWordsFilter;
+    use crate::storage::{MemoryStorage, PlayerStats, Storage};
+    use std::sync::Arc;
+    use tokio::time::{Duration, Instant, sleep_until, timeout};
+
+    fn test_config() -> CoordinatorConfig {
+        CoordinatorConfig {
+            room_idle_ttl: Duration::from_secs(60 * 60),
+            reconnect_grace: Duration::from_secs(60),
+            match_timeout: Duration::from_secs(60),
+            game_ids: dict::GameIdGenerator::new(dict::DEFAULT_GAME_ID_LENGTH, dict::DEFAULT_GAME_ID_ALPHABET).unwrap(),
+            chat_filter: Arc::new(BannedWordsFilter::default()),
+        }
+    }
+
+    fn profile(handle: &str) -> PublicPlayer {
+        PublicPlayer::new(handle.to_string(), None, None).unwrap()
+    }
+
+    fn handles(board_state: &BoardState) -> Vec<&str> {
+        board_state.players.iter().map(PublicPlayer::handle).collect()
+    }
+
+    /// Lets the storage writes game actors spawn catch up.
+    async fn settle() {
+        sleep_until(Instant::now() + Duration::from_millis(20)).await;
+    }
+
+    async fn next_message(
+        rx: &mut mpsc::Receiver<ServerMessage>,
+        wanted: impl Fn(&ServerMessage) -> bool,
+    ) -> ServerMessage {
+        loop {
+            let message = timeout(Duration::from_secs(3), rx.recv())
+                .await
+                .expect("Timed out waiting for a message")
+                .expect("Player channel closed");
+            if wanted(&message) {
+                return message;
+            }
+        }
+    }
+
+    /// Starts a coordinator and creates a room for `p1`, returning its id.
+    async fn create_game(
+        storage: Arc<MemoryStorage>,
+        config: CoordinatorConfig,
+    ) -> (CommandSender, GameId, PlayerSender, mpsc::Receiver<ServerMessage>) {
+        let (tx, coordinator) = GameCoordinator::new(storage, config);
+        tokio::spawn(coordinator.run());
+
+        let (player_tx, mut player_rx) = mpsc::channel(32);
+        let create = GameCommand::Create {
+            player_id: "p1".to_string(),
+            user_id: "u1".to_string(),
+            profile: profile("host"),
+            options: RoomOptions::default(),
+            reply_sender: ReplySender::new(player_tx.clone(), None),
+        };
+        tx.send(create).await.unwrap();
+        let ServerMessage::Created { game_id, .. } = next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::Created { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        (tx, game_id, player_tx, player_rx)
+    }
+
+    #[tokio::test]
+    async fn created_games_are_saved() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (_tx, game_id, _player_tx, _player_rx) = create_game(storage.clone(), test_config()).await;
+        settle().await;
+
+        let record = storage.assert_game_saved(&game_id);
+        assert_eq!(record.game_id, game_id);
+        assert_eq!(handles(&record.board_state), vec!["host"]);
+    }
+
+    #[tokio::test]
+    async fn finished_rounds_are_recorded_with_player_stats() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, player_tx, mut player_rx) = create_game(storage.clone(), test_config()).await;
+        settle().await;
+
+        let solution = storage.assert_game_saved(&game_id).solution_word;
+        let guess = GameCommand::Guess {
+            game_id: game_id.clone(),
+            player_id: "p1".to_string(),
+            word: solution,
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(guess).await.unwrap();
+        next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::BoardDelta { .. })
+        })
+        .await;
+        settle().await;
+
+        let rounds = storage.assert_rounds(&game_id, 1);
+        assert!(matches!(rounds[0].game_status, GameStatus::Won));
+        storage.assert_player_stats(
+            "u1",
+            PlayerStats {
+                games_played: 1,
+                games_won: 1,
+                win_percentage: 100,
+                current_streak: 1,
+                max_streak: 1,
+                guess_distribution: vec![1, 0, 0, 0, 0, 0],
+                last_daily_word: None,
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn stored_games_are_restored_on_join() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (_tx, game_id, _player_tx, _player_rx) = create_game(storage.clone(), test_config()).await;
+        settle().await;
+
+        // A fresh coordinator stands in for a restarted server.
+        let (tx, coordinator) = GameCoordinator::new(storage.clone(), test_config());
+        tokio::spawn(coordinator.run());
+        let (player_tx, mut player_rx) = mpsc::channel(32);
+        let join = GameCommand::Join {
+            game_id: game_id.clone(),
+            player_id: "p2".to_string(),
+            user_id: "u2".to_string(),
+            profile: profile("guest"),
+            old_player_id: None,
+            password: None,
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(join).await.unwrap();
+
+        let ServerMessage::Joined {
+            game_id: joined_id,
+            board_state,
+            ..
+        } = next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::Joined { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        assert_eq!(joined_id, game_id);
+        assert_eq!(handles(&board_state), vec!["guest"]);
+    }
+
+    #[tokio::test]
+    async fn joining_an_unknown_game_fails() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, coordinator) = GameCoordinator::new(storage.clone(), test_config());
+        tokio::spawn(coordinator.run());
+        let (player_tx, mut player_rx) = mpsc::channel(32);
+        let join = GameCommand::Join {
+            game_id: "NOPE".to_string(),
+            player_id: "p1".to_string(),
+            user_id: "u1".to_string(),
+            profile: profile("host"),
+            old_player_id: None,
+            password: None,
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(join).await.unwrap();
+
+        let error = next_message(&mut player_rx, |_| true).await;
+        assert!(matches!(
+            error,
+            ServerMessage::Error {
+                error: GameError::JoinError { .. },
+                ..
+            }
+        ));
+        assert!(storage.load_game("NOPE").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn new_games_are_refused_once_shutting_down() {
+        let (tx, coordinator) = GameCoordinator::new(Arc::new(MemoryStorage::new()), test_config());
+        tokio::spawn(coordinator.run());
+        tx.send(GameCommand::Shutdown).await.unwrap();
+
+        let (player_tx, mut player_rx) = mpsc::channel(32);
+        let create = GameCommand::Create {
+            player_id: "p1".to_string(),
+            user_id: "u1".to_string(),
+            profile: profile("host"),
+            options: RoomOptions::default(),
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(create).await.unwrap();
+        let error = next_message(&mut player_rx, |_| true).await;
+        assert!(matches!(
+            error,
+            ServerMessage::Error {
+                error: GameError::JoinError { .. },
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn rooms_closed_for_a_restart_stay_resumable() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, _player_tx, mut player_rx) = create_game(storage.clone(), test_config()).await;
+        let (done, mut closed) = mpsc::channel(1);
+        let close_all = GameCommand::CloseAll {
+            reason: "Server restarted".to_string(),
+            done,
+        };
+        tx.send(close_all).await.unwrap();
+
+        let closed_message = next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::RoomClosed { .. })
+        })
+        .await;
+        assert!(matches!(
+            closed_message,
+            ServerMessage::RoomClosed { resumable: true, .. }
+        ));
+        timeout(Duration::from_secs(3), closed.recv())
+            .await
+            .expect("Rooms never finished closing");
+        storage.assert_game_saved(&game_id);
+    }
+
+    #[tokio::test]
+    async fn rooms_are_forgotten_once_the_last_player_leaves() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, player_tx, mut player_rx) = create_game(storage.clone(), test_config()).await;
+        let disconnect = GameCommand::Disconnect {
+            game_id: game_id.clone(),
+            player_id: "p1".to_string(),
+        };
+        tx.send(disconnect).await.unwrap();
+        settle().await;
+        storage.assert_game_deleted(&game_id);
+
+        let join = GameCommand::Join {
+            game_id,
+            player_id: "p2".to_string(),
+            user_id: "u2".to_string(),
+            profile: profile("guest"),
+            old_player_id: None,
+            password: None,
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(join).await.unwrap();
+        let error = next_message(&mut player_rx, |message| matches!(message, ServerMessage::Error { .. })).await;
+        assert!(matches!(
+            error,
+            ServerMessage::Error { error: GameError::JoinError { message }, .. } if message == "Game doesnt exist"
+        ));
+    }
+
+    #[tokio::test]
+    async fn idle_rooms_are_reaped_and_forgotten() {
+        let storage = Arc::new(MemoryStorage::new());
+        let config = CoordinatorConfig {
+            room_idle_ttl: Duration::from_millis(1),
+            ..test_config()
+        };
+        let (_tx, game_id, _player_tx, mut player_rx) = create_game(storage.clone(), config).await;
+        let closed = next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::RoomClosed { .. })
+        })
+        .await;
+        assert!(matches!(closed, ServerMessage::RoomClosed { resumable: false, .. }));
+        settle().await;
+        storage.assert_game_deleted(&game_id);
+    }
+
+    /// Has `player_id` join `game_id`, returning their channel once the join went through.
+    async fn join_game(
+        tx: &CommandSender,
+        game_id: &str,
+        player_id: &str,
+        handle: &str,
+        old_player_id: Option<&str>,
+    ) -> (PlayerSender, mpsc::Receiver<ServerMessage>, BoardState) {
+        let (player_tx, mut player_rx) = mpsc::channel(32);
+        let join = GameCommand::Join {
+            game_id: game_id.to_string(),
+            player_id: player_id.to_string(),
+            user_id: format!("user-{player_id}"),
+            profile: profile(handle),
+            old_player_id: old_player_id.map(str::to_string),
+            password: None,
+            reply_sender: ReplySender::new(player_tx.clone(), None),
+        };
+        tx.send(join).await.unwrap();
+        let ServerMessage::Joined { board_state, .. } = next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::Joined { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        (player_tx, player_rx, board_state)
+    }
+
+    #[tokio::test]
+    async fn dropped_players_can_take_their_seat_back() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, _player_tx, _player_rx) = create_game(storage, test_config()).await;
+        let (_guest_tx, mut guest_rx, _) = join_game(&tx, &game_id, "p2", "guest", None).await;
+
+        let lost = GameCommand::ConnectionLost {
+            game_id: game_id.clone(),
+            player_id: "p1".to_string(),
+        };
+        tx.send(lost).await.unwrap();
+        let ServerMessage::GameUpdate { board_state, .. } = next_message(&mut guest_rx, |message| {
+            matches!(message, ServerMessage::GameUpdate { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        assert!(board_state.players[0].away);
+        assert_eq!(handles(&board_state), vec!["host", "guest"]);
+
+        let (_player_tx, _player_rx, board_state) = join_game(&tx, &game_id, "p1-again", "host", Some("p1")).await;
+        assert_eq!(handles(&board_state), vec!["host", "guest"]);
+        assert_eq!(board_state.current_turn, "host");
+        assert!(!board_state.players[0].away);
+    }
+
+    #[tokio::test]
+    async fn away_players_leave_once_the_grace_period_ends() {
+        let storage = Arc::new(MemoryStorage::new());
+        let config = CoordinatorConfig {
+            reconnect_grace: Duration::from_millis(50),
+            ..test_config()
+        };
+        let (tx, game_id, _player_tx, _player_rx) = create_game(storage, config).await;
+        let (_guest_tx, mut guest_rx, _) = join_game(&tx, &game_id, "p2", "guest", None).await;
+
+        let lost = GameCommand::ConnectionLost {
+            game_id,
+            player_id: "p1".to_string(),
+        };
+        tx.send(lost).await.unwrap();
+        let ServerMessage::Exited { board_state } =
+            next_message(&mut guest_rx, |message| matches!(message, ServerMessage::Exited { .. })).await
+        else {
+            unreachable!()
+        };
+        assert_eq!(handles(&board_state), vec!["guest"]);
+    }
+
+    #[tokio::test]
+    async fn a_replayed_resume_only_reclaims_a_seat_once() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, _player_tx, _player_rx) = create_game(storage, test_config()).await;
+
+        let (_resumed_tx, _resumed_rx, board_state) = join_game(&tx, &game_id, "p1-again", "host", Some("p1")).await;
+        assert_eq!(handles(&board_state), vec!["host"]);
+
+        // The same token again, its player id no longer holds the seat so the joiner gets a handle of their own.
+        let (_replay_tx, _replay_rx, board_state) = join_game(&tx, &game_id, "intruder", "host", Some("p1")).await;
+        assert_eq!(board_state.players.len(), 2);
+        assert_eq!(board_state.players[0].handle(), "host");
+        assert_ne!(board_state.players[1].handle(), "host");
+        assert_eq!(board_state.current_turn, "host");
+
+        // Once the room is full a third replay is turned away like any other joiner.
+        let (replay_tx, mut replay_rx) = mpsc::channel(32);
+        let replay = GameCommand::Join {
+            game_id,
+            player_id: "another-intruder".to_string(),
+            user_id: "user-another-intruder".to_string(),
+            profile: profile("host"),
+            old_player_id: Some("p1".to_string()),
+            password: None,
+            reply_sender: ReplySender::new(replay_tx, None),
+        };
+        tx.send(replay).await.unwrap();
+        let message = next_message(&mut replay_rx, |message| {
+            matches!(message, ServerMessage::Joined { .. } | ServerMessage::Error { .. })
+        })
+        .await;
+        assert!(matches!(
+            message,
+            ServerMessage::Error {
+                error: GameError::JoinError { .. },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn profiles_are_validated() {
+        let player = PublicPlayer::new(
+            "h".to_string(),
+            Some("  Ada L_-1 ".to_string()),
+            Some("#a1B2c3".to_string()),
+        )
+        .unwrap();
+        assert_eq!(player.display_name, "Ada L_-1");
+        assert_eq!(
+            PublicPlayer::new("h".to_string(), None, None).unwrap().display_name,
+            "Player"
+        );
+
+        for name in ["", "   ", "a name that is far too long", "<script>"] {
+            assert!(matches!(
+                PublicPlayer::new("h".to_string(), Some(name.to_string()), None),
+                Err(GameError::ProfileError { .. })
+            ));
+        }
+        for color in ["red", "#12345", "#1234567", "#12345g"] {
+            assert!(matches!(
+                PublicPlayer::new("h".to_string(), None, Some(color.to_string())),
+                Err(GameError::ProfileError { .. })
+            ));
+        }
+    }
+
+    async fn next_error(rx: &mut mpsc::Receiver<ServerMessage>) -> GameError {
+        let ServerMessage::Error { error, .. } =
+            next_message(rx, |message| matches!(message, ServerMessage::Error { .. })).await
+        else {
+            unreachable!()
+        };
+        error
+    }
+
+    #[tokio::test]
+    async fn only_the_host_can_kick_and_kicked_players_are_told() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, player_tx, mut player_rx) = create_game(storage, test_config()).await;
+        let (guest_tx, mut guest_rx, board_state) = join_game(&tx, &game_id, "p2", "guest", None).await;
+        assert_eq!(board_state.host, "host");
+
+        let kick = GameCommand::Kick {
+            game_id: game_id.clone(),
+            player_id: "p2".to_string(),
+            target: "host".to_string(),
+            reply_sender: ReplySender::new(guest_tx, None),
+        };
+        tx.send(kick).await.unwrap();
+        assert!(matches!(
+            next_error(&mut guest_rx).await,
+            GameError::PermissionDenied { .. }
+        ));
+
+        let kick = GameCommand::Kick {
+            game_id,
+            player_id: "p1".to_string(),
+            target: "guest".to_string(),
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(kick).await.unwrap();
+        next_message(&mut guest_rx, |message| matches!(message, ServerMessage::Kicked { .. })).await;
+        let ServerMessage::Exited { board_state } = next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::Exited { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        assert_eq!(handles(&board_state), vec!["host"]);
+    }
+
+    #[tokio::test]
+    async fn locked_rooms_turn_new_players_away() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, player_tx, _player_rx) = create_game(storage, test_config()).await;
+        let lock = GameCommand::Lock {
+            game_id: game_id.clone(),
+            player_id: "p1".to_string(),
+            locked: true,
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(lock).await.unwrap();
+
+        let (guest_tx, mut guest_rx) = mpsc::channel(32);
+        let join = GameCommand::Join {
+            game_id,
+            player_id: "p2".to_string(),
+            user_id: "u2".to_string(),
+            profile: profile("guest"),
+            old_player_id: None,
+            password: None,
+            reply_sender: ReplySender::new(guest_tx, None),
+        };
+        tx.send(join).await.unwrap();
+        assert!(matches!(
+            next_error(&mut guest_rx).await,
+            GameError::JoinError { message } if message == "Room is locked"
+        ));
+    }
+
+    #[tokio::test]
+    async fn the_host_can_hand_over_the_room_and_its_settings() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, player_tx, _player_rx) = create_game(storage, test_config()).await;
+        let (guest_tx, mut guest_rx, _) = join_game(&tx, &game_id, "p2", "guest", None).await;
+
+        let transfer = GameCommand::TransferHost {
+            game_id: game_id.clone(),
+            player_id: "p1".to_string(),
+            target: "guest".to_string(),
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(transfer).await.unwrap();
+
+        let settings = GameSettings {
+            hard_mode: true,
+            ..GameSettings::default()
+        };
+        let update = GameCommand::UpdateSettings {
+            game_id: game_id.clone(),
+            player_id: "p2".to_string(),
+            settings: settings.clone(),
+            reply_sender: ReplySender::new(guest_tx.clone(), None),
+        };
+        tx.send(update).await.unwrap();
+        let ServerMessage::GameUpdate { board_state, .. } = next_message(&mut guest_rx, |message| {
+            matches!(message, ServerMessage::GameUpdate { board_state, .. } if board_state.settings.hard_mode)
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        assert_eq!(board_state.host, "guest");
+        assert_eq!(board_state.settings, settings);
+
+        let update = GameCommand::UpdateSettings {
+            game_id,
+            player_id: "p2".to_string(),
+            settings: GameSettings {
+                word_length: 6,
+                ..settings
+            },
+            reply_sender: ReplySender::new(guest_tx, None),
+        };
+        tx.send(update).await.unwrap();
+        assert!(matches!(
+            next_error(&mut guest_rx).await,
+            GameError::SettingsError { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn hard_mode_guesses_must_use_revealed_hints() {
+        let (player_tx, _player_rx) = mpsc::channel(32);
+        let (rooms, _rooms_rx) = mpsc::unbounded_channel();
+        let context = GameContext {
+            storage: Arc::new(MemoryStorage::new()),
+            reconnect_grace: Duration::from_secs(60),
+            rooms,
+            chat_filter: Arc::new(BannedWordsFilter::default()),
+        };
+        let (_tx, mut game) = Game::new(
+            "GAME".to_string(),
+            "p1".to_string(),
+            "u1".to_string(),
+            profile("host"),
+            RoomOptions::default(),
+            player_tx,
+            context,
+        );
+        game.board_state.guesses.push(GuessResult {
+            word: "CRANE".to_string(),
+            status: vec![
+                GameColor::Green,
+                GameColor::Yellow,
+                GameColor::Gray,
+                GameColor::Gray,
+                GameColor::Gray,
+            ],
+            reactions: BTreeMap::new(),
+        });
+
+        assert!(game.check_hard_mode("CHARM").is_ok());
+        assert!(game.check_hard_mode("crony").is_ok());
+        assert!(matches!(
+            game.check_hard_mode("TRAIN"),
+            Err(GameError::GuessError { message }) if message == "Letter 1 must be C"
+        ));
+        assert!(matches!(
+            game.check_hard_mode("CLOTH"),
+            Err(GameError::GuessError { message }) if message == "Guess must contain R"
+        ));
+    }
+
+    #[tokio::test]
+    async fn password_rooms_only_let_in_players_with_the_password() {
+        let (tx, coordinator) = GameCoordinator::new(Arc::new(MemoryStorage::new()), test_config());
+        tokio::spawn(coordinator.run());
+        let (player_tx, mut player_rx) = mpsc::channel(32);
+        let create = GameCommand::Create {
+            player_id: "p1".to_string(),
+            user_id: "u1".to_string(),
+            profile: profile("host"),
+            options: RoomOptions {
+                password_hash: session::hash_room_password(Some("hunter2".to_string())).await.unwrap(),
+                ..RoomOptions::default()
+            },
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(create).await.unwrap();
+        let ServerMessage::Created { game_id, .. } = next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::Created { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+
+        let (guest_tx, mut guest_rx) = mpsc::channel(32);
+        for (password, expected) in [
+            (None, "This room needs a password"),
+            (Some("wrong"), "Incorrect room password"),
+        ] {
+            let join = GameCommand::Join {
+                game_id: game_id.clone(),
+                player_id: "p2".to_string(),
+                user_id: "u2".to_string(),
+                profile: profile("guest"),
+                old_player_id: None,
+                password: password.map(str::to_string),
+                reply_sender: ReplySender::new(guest_tx.clone(), None),
+            };
+            tx.send(join).await.unwrap();
+            assert!(matches!(
+                next_error(&mut guest_rx).await,
+                GameError::PasswordError { message } if message == expected
+            ));
+        }
+
+        let join = GameCommand::Join {
+            game_id,
+            player_id: "p2".to_string(),
+            user_id: "u2".to_string(),
+            profile: profile("guest"),
+            old_player_id: None,
+            password: Some("hunter2".to_string()),
+            reply_sender: ReplySender::new(guest_tx, None),
+        };
+        tx.send(join).await.unwrap();
+        let ServerMessage::Joined { board_state, .. } =
+            next_message(&mut guest_rx, |message| matches!(message, ServerMessage::Joined { .. })).await
+        else {
+            unreachable!()
+        };
+        assert_eq!(handles(&board_state), vec!["host", "guest"]);
+    }
+
+    #[tokio::test]
+    async fn only_players_in_the_room_can_start_a_new_round() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, _player_tx, mut player_rx) = create_game(storage, test_config()).await;
+        let (intruder_tx, mut intruder_rx) = mpsc::channel(8);
+        let new_round = GameCommand::New {
+            game_id,
+            player_id: "intruder".to_string(),
+            reply_sender: ReplySender::new(intruder_tx, None),
+        };
+        tx.send(new_round).await.unwrap();
+
+        assert!(matches!(
+            next_error(&mut intruder_rx).await,
+            GameError::NotInGame { .. }
+        ));
+        settle().await;
+        assert!(
+            std::iter::from_fn(|| player_rx.try_recv().ok())
+                .all(|message| !matches!(message, ServerMessage::NewGame { .. }))
+        );
+    }
+
+    #[tokio::test]
+    async fn stored_game_ids_are_not_reused() {
+        let storage = Arc::new(MemoryStorage::new());
+        let config = CoordinatorConfig {
+            game_ids: dict::GameIdGenerator::new(1, "AB").unwrap(),
+            ..test_config()
+        };
+        let (_tx, game_id, _player_tx, _player_rx) = create_game(storage.clone(), config.clone()).await;
+        settle().await;
+
+        // Fill the only other id too, then come back as if the server had restarted.
+        let mut record = storage.assert_game_saved(&game_id);
+        record.game_id = if game_id == "A" { "B" } else { "A" }.to_string();
+        storage.save_game(record).await.unwrap();
+        let (tx, coordinator) = GameCoordinator::new(storage, config);
+        tokio::spawn(coordinator.run());
+
+        let (player_tx, mut player_rx) = mpsc::channel(32);
+        let create = GameCommand::Create {
+            player_id: "p2".to_string(),
+            user_id: "u2".to_string(),
+            profile: profile("guest"),
+            options: RoomOptions::default(),
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(create).await.unwrap();
+        assert!(matches!(
+            next_error(&mut player_rx).await,
+            GameError::JoinError { message } if message == "Unable to create a room, try again"
+        ));
+    }
+
+    async fn list_rooms(tx: &CommandSender) -> Vec<RoomSummary> {
+        let (player_tx, mut player_rx) = mpsc::channel(32);
+        tx.send(GameCommand::ListRooms {
+            reply_sender: ReplySender::new(player_tx, None),
+        })
+        .await
+        .unwrap();
+        let ServerMessage::RoomList { rooms } = next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::RoomList { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        rooms
+    }
+
+    #[tokio::test]
+    async fn only_public_rooms_with_a_free_seat_are_listed() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, private_id, _player_tx, _player_rx) = create_game(storage, test_config()).await;
+
+        let (host_tx, mut host_rx) = mpsc::channel(32);
+        let create = GameCommand::Create {
+            player_id: "p2".to_string(),
+            user_id: "u2".to_string(),
+            profile: profile("public-host"),
+            options: RoomOptions {
+                public: true,
+                ..RoomOptions::default()
+            },
+            reply_sender: ReplySender::new(host_tx.clone(), None),
+        };
+        tx.send(create).await.unwrap();
+        let ServerMessage::Created { game_id, .. } =
+            next_message(&mut host_rx, |message| matches!(message, ServerMessage::Created { .. })).await
+        else {
+            unreachable!()
+        };
+        settle().await;
+
+        let rooms = list_rooms(&tx).await;
+        assert_eq!(rooms.len(), 1);
+        assert_eq!(rooms[0].game_id, game_id);
+        assert_ne!(rooms[0].game_id, private_id);
+        assert_eq!(rooms[0].player_count, 1);
+
+        join_game(&tx, &game_id, "p3", "guest", None).await;
+        settle().await;
+        assert!(list_rooms(&tx).await.is_empty());
+    }
+
+    #[test]
+    fn locked_or_private_rooms_are_not_open() {
+        let room = RoomSummary {
+            game_id: "GAME".to_string(),
+            player_count: 1,
+            mode: GameMode::Coop,
+            word_length: dict::WORD_LENGTH,
+            status: GameStatus::Waiting,
+            has_password: false,
+            public: true,
+            locked: false,
+        };
+        assert!(room.is_open());
+        assert!(
+            !RoomSummary {
+                locked: true,
+                ..room.clone()
+            }
+            .is_open()
+        );
+        assert!(
+            !RoomSummary {
+                public: false,
+                ..room.clone()
+            }
+            .is_open()
+        );
+        assert!(
+            !RoomSummary {
+                player_count: MAX_PLAYERS,
+                ..room
+            }
+            .is_open()
+        );
+    }
+
+    async fn find_match(tx: &CommandSender, player_id: &str, handle: &str) -> mpsc::Receiver<ServerMessage> {
+        let (player_tx, player_rx) = mpsc::channel(32);
+        let find = GameCommand::FindMatch {
+            player_id: player_id.to_string(),
+            user_id: format!("user-{player_id}"),
+            profile: profile(handle),
+            settings: GameSettings::default(),
+            rating: matchmaking::DEFAULT_RATING,
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(find).await.unwrap();
+        player_rx
+    }
+
+    #[tokio::test]
+    async fn matched_players_share_a_room() {
+        let (tx, coordinator) = GameCoordinator::new(Arc::new(MemoryStorage::new()), test_config());
+        tokio::spawn(coordinator.run());
+        let mut host_rx = find_match(&tx, "p1", "host").await;
+        assert!(matches!(
+            next_message(&mut host_rx, |_| true).await,
+            ServerMessage::MatchQueued { position: 1 }
+        ));
+
+        let mut guest_rx = find_match(&tx, "p2", "guest").await;
+        let ServerMessage::Joined { game_id, .. } =
+            next_message(&mut host_rx, |message| matches!(message, ServerMessage::Joined { .. })).await
+        else {
+            unreachable!()
+        };
+        let ServerMessage::Joined {
+            game_id: joined_id,
+            board_state,
+            ..
+        } = next_message(&mut guest_rx, |message| matches!(message, ServerMessage::Joined { .. })).await
+        else {
+            unreachable!()
+        };
+        assert_eq!(joined_id, game_id);
+        assert_eq!(handles(&board_state), vec!["host", "guest"]);
+    }
+
+    #[tokio::test]
+    async fn hosts_matched_with_a_guest_that_left_wait_again() {
+        let (tx, coordinator) = GameCoordinator::new(Arc::new(MemoryStorage::new()), test_config());
+        tokio::spawn(coordinator.run());
+        let mut host_rx = find_match(&tx, "p1", "host").await;
+        next_message(&mut host_rx, |message| {
+            matches!(message, ServerMessage::MatchQueued { .. })
+        })
+        .await;
+
+        drop(find_match(&tx, "p2", "guest").await);
+        let message = next_message(&mut host_rx, |message| {
+            matches!(
+                message,
+                ServerMessage::MatchQueued { .. } | ServerMessage::Joined { .. }
+            )
+        })
+        .await;
+        assert!(matches!(message, ServerMessage::MatchQueued { position: 1 }));
+
+        let mut guest_rx = find_match(&tx, "p3", "guest").await;
+        next_message(&mut guest_rx, |message| matches!(message, ServerMessage::Joined { .. })).await;
+    }
+
+    #[tokio::test]
+    async fn daily_rooms_play_the_word_of_the_day() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, player_tx, mut player_rx) = create_game(storage.clone(), test_config()).await;
+        let update = GameCommand::UpdateSettings {
+            game_id: game_id.clone(),
+            player_id: "p1".to_string(),
+            settings: GameSettings {
+                mode: GameMode::Daily,
+                ..GameSettings::default()
+            },
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(update).await.unwrap();
+        next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::GameUpdate { board_state, .. } if board_state.settings.mode == GameMode::Daily)
+        })
+        .await;
+        settle().await;
+
+        assert_eq!(
+            storage.assert_game_saved(&game_id).solution_word,
+            dict::daily_solution(storage::today())
+        );
+    }
+
+    #[tokio::test]
+    async fn chat_is_filtered_and_replayed_to_players_who_join_later() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, player_tx, mut player_rx) = create_game(storage, test_config()).await;
+        let chat = GameCommand::Chat {
+            game_id: game_id.clone(),
+            player_id: "p1".to_string(),
+            text: "  well shit  ".to_string(),
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(chat).await.unwrap();
+        let ServerMessage::ChatMessage { entry } = next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::ChatMessage { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        assert_eq!((entry.from.as_str(), entry.text.as_str()), ("host", "well ****"));
+
+        let (_guest_tx, mut guest_rx, _) = join_game(&tx, &game_id, "p2", "guest", None).await;
+        let ServerMessage::ChatMessage { entry } = next_message(&mut guest_rx, |message| {
+            matches!(message, ServerMessage::ChatMessage { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        assert_eq!(entry.text, "well ****");
+    }
+
+    #[tokio::test]
+    async fn outsiders_cannot_chat() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, _player_tx, mut player_rx) = create_game(storage, test_config()).await;
+        let (intruder_tx, mut intruder_rx) = mpsc::channel(8);
+        let chat = GameCommand::Chat {
+            game_id,
+            player_id: "intruder".to_string(),
+            text: "hello".to_string(),
+            reply_sender: ReplySender::new(intruder_tx, None),
+        };
+        tx.send(chat).await.unwrap();
+        assert!(matches!(
+            next_error(&mut intruder_rx).await,
+            GameError::NotInGame { .. }
+        ));
+        settle().await;
+        assert!(player_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn players_react_to_a_guess_once_and_outsiders_not_at_all() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, player_tx, mut player_rx) = create_game(storage, test_config()).await;
+        let guess = GameCommand::Guess {
+            game_id: game_id.clone(),
+            player_id: "p1".to_string(),
+            word: "crane".to_string(),
+            reply_sender: ReplySender::new(player_tx.clone(), None),
+        };
+        tx.send(guess).await.unwrap();
+        next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::BoardDelta { .. })
+        })
+        .await;
+
+        let react = |player_id: &str, reply_sender: PlayerSender| GameCommand::React {
+            game_id: game_id.clone(),
+            player_id: player_id.to_string(),
+            guess_index: 0,
+            emoji: REACTION_EMOJIS[0].to_string(),
+            reply_sender: ReplySender::new(reply_sender, None),
+        };
+        tx.send(react("p1", player_tx.clone())).await.unwrap();
+        tx.send(react("p1", player_tx.clone())).await.unwrap();
+        let ServerMessage::Reaction { count, from, .. } = next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::Reaction { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        assert_eq!((count, from.as_str()), (1, "host"));
+
+        let (intruder_tx, mut intruder_rx) = mpsc::channel(8);
+        tx.send(react("intruder", intruder_tx)).await.unwrap();
+        assert!(matches!(
+            next_error(&mut intruder_rx).await,
+            GameError::NotInGame { .. }
+        ));
+        settle().await;
+        assert!(player_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn drafts_reach_teammates_only_from_the_player_whose_turn_it_is() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, _player_tx, mut player_rx) = create_game(storage, test_config()).await;
+        let (_guest_tx, mut guest_rx, _) = join_game(&tx, &game_id, "p2", "guest", None).await;
+        next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::Joined { .. })
+        })
+        .await;
+
+        let draft = |player_id: &str, letters: &str| GameCommand::Draft {
+            game_id: game_id.clone(),
+            player_id: player_id.to_string(),
+            letters: letters.to_string(),
+        };
+        tx.send(draft("p2", "sl")).await.unwrap();
+        tx.send(draft("p1", "cr")).await.unwrap();
+        let ServerMessage::Draft { from, letters } =
+            next_message(&mut guest_rx, |message| matches!(message, ServerMessage::Draft { .. })).await
+        else {
+            unreachable!()
+        };
+        assert_eq!((from.as_str(), letters.as_str()), ("host", "CR"));
+
+        settle().await;
+        assert!(player_rx.try_recv().is_err());
+        assert!(guest_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn teammates_suggest_words_the_active_player_can_accept() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, player_tx, mut player_rx) = create_game(storage, test_config()).await;
+        let (guest_tx, mut guest_rx, _) = join_game(&tx, &game_id, "p2", "guest", None).await;
+
+        let suggest = GameCommand::Suggest {
+            game_id: game_id.clone(),
+            player_id: "p2".to_string(),
+            word: "crane".to_string(),
+            reply_sender: ReplySender::new(guest_tx, None),
+        };
+        tx.send(suggest).await.unwrap();
+        let ServerMessage::Suggestion { from, word } = next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::Suggestion { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        assert_eq!((from.as_str(), word.as_str()), ("guest", "CRANE"));
+
+        let accept = GameCommand::AcceptSuggestion {
+            game_id,
+            player_id: "p1".to_string(),
+            from,
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(accept).await.unwrap();
+        let ServerMessage::BoardDelta { changes, .. } = next_message(&mut guest_rx, |message| {
+            matches!(message, ServerMessage::BoardDelta { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        assert!(matches!(&changes[0], BoardChange::GuessAdded { guess } if guess.word == "CRANE"));
+    }
+
+    #[tokio::test]
+    async fn outsiders_cannot_suggest() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, _player_tx, mut player_rx) = create_game(storage, test_config()).await;
+        let (intruder_tx, mut intruder_rx) = mpsc::channel(8);
+        let suggest = GameCommand::Suggest {
+            game_id,
+            player_id: "intruder".to_string(),
+            word: "crane".to_string(),
+            reply_sender: ReplySender::new(intruder_tx, None),
+        };
+        tx.send(suggest).await.unwrap();
+        assert!(matches!(
+            next_error(&mut intruder_rx).await,
+            GameError::NotInGame { .. }
+        ));
+        settle().await;
+        assert!(player_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn requests_with_an_id_are_acked_or_answered_with_a_tagged_error() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, player_tx, mut player_rx) = create_game(storage, test_config()).await;
+
+        let guess = |word: &str, request_id: &str| GameCommand::Guess {
+            game_id: game_id.clone(),
+            player_id: "p1".to_string(),
+            word: word.to_string(),
+            reply_sender: ReplySender::new(player_tx.clone(), Some(request_id.to_string())),
+        };
+        tx.send(guess("qqqqq", "1")).await.unwrap();
+        let error = next_message(&mut player_rx, |message| matches!(message, ServerMessage::Error { .. })).await;
+        assert!(matches!(error, ServerMessage::Error { request_id: Some(id), .. } if id == "1"));
+
+        tx.send(guess("crane", "2")).await.unwrap();
+        let ack = next_message(&mut player_rx, |message| matches!(message, ServerMessage::Ack { .. })).await;
+        assert!(matches!(ack, ServerMessage::Ack { request_id } if request_id == "2"));
+    }
+
+    #[tokio::test]
+    async fn guesses_send_deltas_and_only_players_get_snapshots() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (tx, game_id, player_tx, mut player_rx) = create_game(storage, test_config()).await;
+
+        let guess = GameCommand::Guess {
+            game_id: game_id.clone(),
+            player_id: "p1".to_string(),
+            word: "crane".to_string(),
+            reply_sender: ReplySender::new(player_tx.clone(), None),
+        };
+        tx.send(guess).await.unwrap();
+        let ServerMessage::BoardDelta { version, changes } = next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::BoardDelta { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        assert!(matches!(&changes[0], BoardChange::GuessAdded { guess } if guess.word.eq_ignore_ascii_case("crane")));
+
+        let snapshot = GameCommand::Snapshot {
+            game_id: game_id.clone(),
+            player_id: "p1".to_string(),
+            reply_sender: ReplySender::new(player_tx, None),
+        };
+        tx.send(snapshot).await.unwrap();
+        let ServerMessage::GameUpdate { board_state, .. } = next_message(&mut player_rx, |message| {
+            matches!(message, ServerMessage::GameUpdate { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        assert_eq!((board_state.version, board_state.guesses.len()), (version, 1));
+
+        let (intruder_tx, mut intruder_rx) = mpsc::channel(8);
+        let snapshot = GameCommand::Snapshot {
+            game_id,
+            player_id: "intruder".to_string(),
+            reply_sender: ReplySender::new(intruder_tx, None),
+        };
+        tx.send(snapshot).await.unwrap();
+        assert!(matches!(
+            next_error(&mut intruder_rx).await,
+            GameError::NotInGame { .. }
+        ));
+    }
+}
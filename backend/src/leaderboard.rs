@@ -0,0 +1,371 @@
+use crate::game::{GameMode, UserId};
+use crate::storage::{self, PlayerStats, RoundRecord, SECONDS_PER_DAY, SharedStorage, StorageError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::time::{Duration, Instant};
+use ts_rs::TS;
+
+const MAX_LEADERBOARD_ENTRIES: usize = 20;
+/// Players need a few wins before their average guess count means anything.
+const MIN_WINS_FOR_AVERAGE: u32 = 3;
+
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum LeaderboardKind {
+    AverageGuesses,
+    LongestStreak,
+    Rating,
+}
+
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum LeaderboardWindow {
+    #[default]
+    AllTime,
+    /// Since Monday 00:00 UTC.
+    Weekly,
+}
+
+impl LeaderboardWindow {
+    fn starts_at(self, now: u64) -> u64 {
+        match self {
+            LeaderboardWindow::AllTime => 0,
+            LeaderboardWindow::Weekly => {
+                // The epoch fell on a Thursday.
+                let days = now / SECONDS_PER_DAY;
+                (days - (days + 3) % 7) * SECONDS_PER_DAY
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields = nullable)]
+pub struct LeaderboardQuery {
+    pub board: LeaderboardKind,
+    #[serde(default)]
+    #[ts(optional, as = "Option<LeaderboardWindow>")]
+    pub window: LeaderboardWindow,
+    /// Only count rounds played in this mode, ratings are always competitive.
+    pub mode: Option<GameMode>,
+}
+
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardEntry {
+    /// Players on the same value share a rank.
+    pub rank: usize,
+    pub user_id: UserId,
+    pub username: String,
+    pub value: f64,
+    pub rounds_played: u32,
+}
+
+/// Replays `rounds` the way they were recorded into each player's stats, daily replays included.
+fn replay<'a>(rounds: impl Iterator<Item = &'a RoundRecord>) -> HashMap<UserId, PlayerStats> {
+    let mut player_stats: HashMap<UserId, PlayerStats> = HashMap::new();
+    for round in rounds {
+        for user_id in &round.players {
+            player_stats
+                .entry(user_id.clone())
+                .or_default()
+                .record_round(user_id, round);
+        }
+    }
+    player_stats
+}
+
+/// Orders `scores` of `(user_id, value, rounds_played)` into a leaderboard of registered players, guests
+/// have no username to show so they are left out.
+fn rank(
+    scores: Vec<(UserId, f64, u32)>,
+    mut usernames: HashMap<UserId, String>,
+    lowest_first: bool,
+) -> Vec<LeaderboardEntry> {
+    let mut scores: Vec<(String, UserId, f64, u32)> = scores
+        .into_iter()
+        .filter_map(|(user_id, value, rounds)| Some((usernames.remove(&user_id)?, user_id, value, rounds)))
+        .collect();
+    scores.sort_by(|(a_name, _, a_value, a_rounds), (b_name, _, b_value, b_rounds)| {
+        let by_value = if lowest_first {
+            a_value.total_cmp(b_value)
+        } else {
+            b_value.total_cmp(a_value)
+        };
+        by_value.then(b_rounds.cmp(a_rounds)).then(a_name.cmp(b_name))
+    });
+    scores.truncate(MAX_LEADERBOARD_ENTRIES);
+
+    let mut entries: Vec<LeaderboardEntry> = Vec::with_capacity(scores.len());
+    for (index, (username, user_id, value, rounds_played)) in scores.into_iter().enumerate() {
+        let rank = match entries.last() {
+            Some(previous) if previous.value == value => previous.rank,
+            _ => index + 1,
+        };
+        entries.push(LeaderboardEntry {
+            rank,
+            user_id,
+            username,
+            value,
+            rounds_played,
+        });
+    }
+    entries
+}
+
+type LeaderboardCache = HashMap<LeaderboardQuery, (Instant, Vec<LeaderboardEntry>)>;
+
+/// Leaderboards of all-time stats and of this week's rounds, each one is reused until `ttl` has passed.
+#[derive(Debug, Clone)]
+pub struct Leaderboards {
+    storage: SharedStorage,
+    ttl: Duration,
+    cache: Arc<Mutex<LeaderboardCache>>,
+}
+
+impl Leaderboards {
+    pub fn new(storage: SharedStorage, ttl: Duration) -> Self {
+        Leaderboards {
+            storage,
+            ttl,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub async fn get(&self, query: LeaderboardQuery) -> Result<Vec<LeaderboardEntry>, StorageError> {
+        if let Some((computed_at, entries)) = self.cache.lock().unwrap().get(&query)
+            && computed_at.elapsed() < self.ttl
+        {
+            return Ok(entries.clone());
+        }
+
+        let entries = self.compute(query).await?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(query, (Instant::now(), entries.clone()));
+        Ok(entries)
+    }
+
+    async fn compute(&self, query: LeaderboardQuery) -> Result<Vec<LeaderboardEntry>, StorageError> {
+        let mode = match query.board {
+            LeaderboardKind::Rating => Some(GameMode::Competitive),
+            _ => query.mode,
+        };
+        // All-time boards read the running totals, only the current week is rebuilt from its rounds.
+        let player_stats = match query.window {
+            LeaderboardWindow::AllTime => self.storage.load_all_player_stats(mode).await?,
+            LeaderboardWindow::Weekly => {
+                let rounds = self
+                    .storage
+                    .load_rounds(query.window.starts_at(storage::now_secs()))
+                    .await?;
+                replay(rounds.iter().filter(|round| mode.is_none_or(|mode| round.mode == mode)))
+            }
+        };
+
+        let scores: Vec<(UserId, f64, u32)> = match query.board {
+            LeaderboardKind::AverageGuesses => player_stats
+                .into_iter()
+                .filter(|(_, stats)| stats.games_won >= MIN_WINS_FOR_AVERAGE)
+                .map(|(user_id, stats)| {
+                    let average = stats.winning_guesses() as f64 / stats.games_won as f64;
+                    (user_id, average, stats.games_played)
+                })
+                .collect(),
+            LeaderboardKind::LongestStreak => player_stats
+                .into_iter()
+                .filter(|(_, stats)| stats.max_streak > 0)
+                .map(|(user_id, stats)| (user_id, stats.max_streak as f64, stats.games_played))
+                .collect(),
+            LeaderboardKind::Rating => {
+                let weekly = query.window == LeaderboardWindow::Weekly;
+                self.storage
+                    .load_ratings()
+                    .await?
+                    .into_iter()
+                    .filter(|(user_id, _)| !weekly || player_stats.contains_key(user_id))
+                    .map(|(user_id, rating)| {
+                        let rounds = player_stats.get(&user_id).map_or(0, |stats| stats.games_played);
+                        (user_id, rating, rounds)
+                    })
+                    .collect()
+            }
+        };
+
+        let user_ids = scores.iter().map(|(user_id, _, _)| user_id.clone()).collect();
+        let usernames = self.storage.load_usernames(user_ids).await?;
+        Ok(rank(scores, usernames, query.board == LeaderboardKind::AverageGuesses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameStatus;
+    use crate::storage::{AccountRecord, MemoryStorage, Storage};
+
+    /// 2024-01-01 00:00 UTC, a Monday.
+    const MONDAY: u64 = 1_704_067_200;
+
+    fn usernames(user_ids: &[&str]) -> HashMap<UserId, String> {
+        user_ids
+            .iter()
+            .map(|user_id| (user_id.to_string(), user_id.to_uppercase()))
+            .collect()
+    }
+
+    fn score(user_id: &str, value: f64, rounds: u32) -> (UserId, f64, u32) {
+        (user_id.to_string(), value, rounds)
+    }
+
+    fn ranks(entries: &[LeaderboardEntry]) -> Vec<(usize, &str)> {
+        entries
+            .iter()
+            .map(|entry| (entry.rank, entry.user_id.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn highest_values_rank_first_unless_lower_is_better() {
+        let scores = vec![score("a", 3.5, 4), score("b", 5.0, 4), score("c", 4.0, 4)];
+        let entries = rank(scores.clone(), usernames(&["a", "b", "c"]), false);
+        assert_eq!(ranks(&entries), vec![(1, "b"), (2, "c"), (3, "a")]);
+        assert_eq!(entries[0].username, "B");
+
+        let entries = rank(scores, usernames(&["a", "b", "c"]), true);
+        assert_eq!(ranks(&entries), vec![(1, "a"), (2, "c"), (3, "b")]);
+    }
+
+    #[test]
+    fn tied_players_share_a_rank() {
+        let scores = vec![
+            score("a", 5.0, 2),
+            score("b", 5.0, 9),
+            score("c", 7.0, 1),
+            score("d", 4.0, 3),
+        ];
+        let entries = rank(scores, usernames(&["a", "b", "c", "d"]), false);
+        // Ties are listed by how many rounds were played, the rank stays the same.
+        assert_eq!(ranks(&entries), vec![(1, "c"), (2, "b"), (2, "a"), (4, "d")]);
+    }
+
+    #[test]
+    fn guests_are_left_off() {
+        let scores = vec![score("guest", 9.0, 10), score("member", 2.0, 1)];
+        let entries = rank(scores, usernames(&["member"]), false);
+        assert_eq!(ranks(&entries), vec![(1, "member")]);
+    }
+
+    async fn register(storage: &MemoryStorage, user_id: &str) {
+        let account = AccountRecord {
+            user_id: user_id.to_string(),
+            username: user_id.to_uppercase(),
+            password_hash: String::new(),
+            created_at: 0,
+        };
+        assert!(storage.create_account(account).await.unwrap());
+    }
+
+    async fn play(storage: &MemoryStorage, mode: GameMode, solution_word: &str, guesses: usize, winners: &[&str]) {
+        let round = RoundRecord {
+            game_id: "ABCD".to_string(),
+            solution_word: solution_word.to_string(),
+            guesses: vec![solution_word.to_string(); guesses],
+            players: vec!["a".to_string(), "b".to_string()],
+            winners: winners.iter().map(|winner| winner.to_string()).collect(),
+            mode,
+            game_status: if winners.is_empty() {
+                GameStatus::Lost
+            } else {
+                GameStatus::Won
+            },
+            finished_at: storage::now_secs(),
+        };
+        for user_id in &round.players {
+            storage.record_player_stats(user_id, &round).await.unwrap();
+        }
+        storage.append_round(round).await.unwrap();
+    }
+
+    fn query(board: LeaderboardKind, window: LeaderboardWindow, mode: Option<GameMode>) -> LeaderboardQuery {
+        LeaderboardQuery { board, window, mode }
+    }
+
+    #[tokio::test]
+    async fn boards_are_computed_per_mode_and_window() {
+        let memory = Arc::new(MemoryStorage::new());
+        register(&memory, "a").await;
+        register(&memory, "b").await;
+        play(&memory, GameMode::Daily, "crane", 3, &["a", "b"]).await;
+        // Replaying the same daily puzzle doesn't count.
+        play(&memory, GameMode::Daily, "crane", 1, &["a", "b"]).await;
+        play(&memory, GameMode::Competitive, "slate", 2, &["a"]).await;
+        play(&memory, GameMode::Competitive, "pious", 4, &["b"]).await;
+        let leaderboards = Leaderboards::new(memory.clone(), Duration::from_secs(60));
+
+        for window in [LeaderboardWindow::AllTime, LeaderboardWindow::Weekly] {
+            let daily = leaderboards
+                .get(query(LeaderboardKind::LongestStreak, window, Some(GameMode::Daily)))
+                .await
+                .unwrap();
+            assert_eq!(ranks(&daily), vec![(1, "a"), (1, "b")]);
+            assert!(daily.iter().all(|entry| entry.value == 1.0 && entry.rounds_played == 1));
+
+            let streaks = leaderboards
+                .get(query(LeaderboardKind::LongestStreak, window, None))
+                .await
+                .unwrap();
+            assert_eq!(ranks(&streaks), vec![(1, "a"), (2, "b")]);
+            assert_eq!(
+                (streaks[0].value, streaks[0].rounds_played, streaks[1].value),
+                (2.0, 3, 1.0)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn average_guesses_need_a_few_wins() {
+        let memory = Arc::new(MemoryStorage::new());
+        register(&memory, "a").await;
+        register(&memory, "b").await;
+        for (word, guesses) in [("crane", 2), ("slate", 3), ("pious", 4)] {
+            play(&memory, GameMode::Competitive, word, guesses, &["a"]).await;
+        }
+        let leaderboards = Leaderboards::new(memory.clone(), Duration::from_secs(60));
+
+        let average = leaderboards
+            .get(query(
+                LeaderboardKind::AverageGuesses,
+                LeaderboardWindow::AllTime,
+                Some(GameMode::Competitive),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(ranks(&average), vec![(1, "a")]);
+        assert_eq!(average[0].value, 3.0);
+    }
+
+    #[test]
+    fn weekly_window_starts_on_the_last_monday() {
+        assert_eq!(LeaderboardWindow::Weekly.starts_at(MONDAY), MONDAY);
+        assert_eq!(
+            LeaderboardWindow::Weekly.starts_at(MONDAY + 6 * SECONDS_PER_DAY + 1),
+            MONDAY
+        );
+        assert_eq!(
+            LeaderboardWindow::Weekly.starts_at(MONDAY - 1),
+            MONDAY - 7 * SECONDS_PER_DAY
+        );
+    }
+
+    #[test]
+    fn all_time_window_starts_at_the_epoch() {
+        assert_eq!(LeaderboardWindow::AllTime.starts_at(MONDAY), 0);
+    }
+}
@@ -1,27 +1,88 @@
+mod auth;
+mod chat;
 mod dict;
 mod game;
+mod leaderboard;
+mod matchmaking;
+mod protocol;
+mod schema;
+mod session;
+mod storage;
 mod websocket;
 
+use auth::AuthState;
 use axum::{
-    self, Router,
-    extract::{FromRequestParts, State, WebSocketUpgrade},
-    http::{StatusCode, header::ORIGIN, request::Parts},
+    self, Json, Router,
+    extract::{FromRef, FromRequestParts, Path, Query, State, WebSocketUpgrade},
+    http::{
+        HeaderMap, HeaderValue, Method, StatusCode,
+        header::{AUTHORIZATION, CONTENT_TYPE, ORIGIN},
+        request::Parts,
+    },
     response::IntoResponse,
-    routing::get,
+    routing::{get, post},
 };
+use chat::BannedWordsFilter;
+use dict::GameIdGenerator;
 use game::CommandSender;
-use game::GameCoordinator;
-use std::env;
-use tokio::{net::TcpListener, signal};
-use tower_http::trace::TraceLayer;
-use tracing::info;
+use game::{CoordinatorConfig, GameCommand, GameCoordinator, GameMode, ReplySender, RoomSummary, ServerMessage};
+use leaderboard::{LeaderboardEntry, LeaderboardKind, LeaderboardQuery, LeaderboardWindow, Leaderboards};
+use serde::Deserialize;
+use session::{ResumeTokens, TokenSigner, UserTokens};
+use std::{
+    env,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+use storage::{MemoryStorage, PlayerStats, SharedStorage, SqliteStorage};
+use tokio::{
+    net::TcpListener,
+    signal,
+    sync::{broadcast, mpsc},
+};
+use tower_http::{
+    cors::{AllowOrigin, CorsLayer},
+    trace::TraceLayer,
+};
+use tracing::{error, info};
 use tracing_subscriber::{self, filter::EnvFilter};
-use websocket::handle_socket;
+use websocket::{SocketContext, handle_socket};
 
 #[derive(Clone, Debug)]
 struct AppState {
     tx: CommandSender,
+    announcements: broadcast::Sender<ServerMessage>,
+    resume_tokens: ResumeTokens,
+    user_tokens: UserTokens,
+    storage: SharedStorage,
+    leaderboards: Leaderboards,
     allowed_origins: Vec<String>,
+    /// Set once the drain starts, sockets opened after the shutdown notice went out would never see it.
+    shutting_down: Arc<AtomicBool>,
+}
+
+impl FromRef<AppState> for AuthState {
+    fn from_ref(state: &AppState) -> Self {
+        AuthState {
+            storage: state.storage.clone(),
+            user_tokens: state.user_tokens.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct SocketParams {
+    token: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LeaderboardParams {
+    #[serde(default)]
+    window: LeaderboardWindow,
+    mode: Option<GameMode>,
 }
 
 struct InvalidOrigin;
@@ -41,12 +102,11 @@ impl FromRequestParts<AppState> for ValidOrigin {
         if state.allowed_origins.is_empty() {
             return Ok(ValidOrigin);
         }
-        if let Some(origin_header) = parts.headers.get(ORIGIN) {
-            if let Ok(origin) = origin_header.to_str() {
-                if state.allowed_origins.contains(&origin.to_string()) {
-                    return Ok(ValidOrigin);
-                }
-            }
+        if let Some(origin_header) = parts.headers.get(ORIGIN)
+            && let Ok(origin) = origin_header.to_str()
+            && state.allowed_origins.contains(&origin.to_string())
+        {
+            return Ok(ValidOrigin);
         }
         Err(InvalidOrigin)
     }
@@ -54,6 +114,13 @@ impl FromRequestParts<AppState> for ValidOrigin {
 
 #[tokio::main]
 async fn main() {
+    // `protocol-types [--check]` regenerates the frontend's protocol definitions instead of serving.
+    let mut args = env::args().skip(1);
+    if args.next().as_deref() == Some("protocol-types") {
+        let check = args.next().as_deref() == Some("--check");
+        std::process::exit(if schema::write_protocol_types(check) { 0 } else { 1 });
+    }
+
     dotenvy::dotenv().expect("Unable to load .env file");
 
     let subscriber = tracing_subscriber::FmtSubscriber::builder()
@@ -78,16 +145,79 @@ async fn main() {
         .map(|s| s.to_string())
         .collect();
 
-    let (tx, game_coordinator) = GameCoordinator::new();
+    let cors = CorsLayer::new()
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers([CONTENT_TYPE, AUTHORIZATION])
+        .allow_origin(if allowed_origins.is_empty() {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::list(
+                allowed_origins
+                    .iter()
+                    .map(|origin| HeaderValue::from_str(origin).expect("ALLOWED_ORIGINS contains an invalid origin")),
+            )
+        });
 
-    let state = AppState { tx, allowed_origins };
+    let storage: SharedStorage = match env::var("SQLITE_PATH") {
+        Ok(path) => {
+            info!("Using SQLite storage at {path}");
+            Arc::new(SqliteStorage::open(&path).expect("Unable to open SQLite database"))
+        }
+        Err(_) => {
+            info!("SQLITE_PATH not set, using in-memory storage");
+            Arc::new(MemoryStorage::new())
+        }
+    };
+
+    let drain_period = env_duration("SHUTDOWN_DRAIN_SECONDS", 10);
+    let config = CoordinatorConfig {
+        room_idle_ttl: env_duration("ROOM_IDLE_TTL_SECONDS", 30 * 60),
+        reconnect_grace: env_duration("RECONNECT_GRACE_SECONDS", 60),
+        match_timeout: env_duration("MATCH_TIMEOUT_SECONDS", 2 * 60),
+        game_ids: GameIdGenerator::new(
+            env_number("GAME_ID_LENGTH", dict::DEFAULT_GAME_ID_LENGTH as u64) as usize,
+            &env::var("GAME_ID_ALPHABET").unwrap_or_else(|_| dict::DEFAULT_GAME_ID_ALPHABET.to_string()),
+        )
+        .expect("GAME_ID_ALPHABET and GAME_ID_LENGTH can't build room codes that aren't blocked"),
+        chat_filter: Arc::new(BannedWordsFilter::default()),
+    };
+
+    let resume_secret = env::var("RESUME_TOKEN_SECRET").ok();
+    if resume_secret.is_none() {
+        info!("RESUME_TOKEN_SECRET not set, resume and player tokens will not survive a restart");
+    }
+    let signer = TokenSigner::new(resume_secret);
+    let resume_tokens = ResumeTokens::new(signer.clone(), env_duration("RESUME_TOKEN_TTL_SECONDS", 60 * 60));
+    let user_tokens = UserTokens::new(signer, env_duration("USER_TOKEN_TTL_SECONDS", 30 * 24 * 60 * 60));
+
+    let leaderboards = Leaderboards::new(storage.clone(), env_duration("LEADERBOARD_CACHE_SECONDS", 60));
+    let (tx, game_coordinator) = GameCoordinator::new(storage.clone(), config);
+    let (announcements, _) = broadcast::channel(16);
+    let shutting_down = Arc::new(AtomicBool::new(false));
+
+    let state = AppState {
+        tx: tx.clone(),
+        announcements: announcements.clone(),
+        resume_tokens,
+        user_tokens,
+        storage,
+        leaderboards,
+        allowed_origins,
+        shutting_down: shutting_down.clone(),
+    };
 
     tracing::info!("Starting Game Server");
     tokio::spawn(game_coordinator.run());
 
     let app = Router::new()
         .route("/ws", get(ws_handler))
+        .route("/rooms", get(rooms_handler))
+        .route("/players/{user_id}/stats", get(player_stats_handler))
+        .route("/leaderboards/{board}", get(leaderboard_handler))
+        .route("/auth/register", post(auth::register))
+        .route("/auth/login", post(auth::login))
         .with_state(state)
+        .layer(cors)
         .layer(TraceLayer::new_for_http());
 
     let app = {
@@ -115,21 +245,147 @@ async fn main() {
     let listener = TcpListener::bind(path).await.unwrap();
     tracing::info!("Listening to: {}", listener.local_addr().unwrap());
     axum::serve(listener, app.into_make_service())
-        .with_graceful_shutdown(shutdown())
+        .with_graceful_shutdown(shutdown(tx, announcements, shutting_down, drain_period))
         .await
         .unwrap();
     tracing::info!("Game Server Shut Down");
 }
 
-async fn shutdown() {
-    signal::ctrl_c().await.expect("Didnt gracfully shut down");
-    tracing::info!("Shutting down because of CTRL+C");
+fn env_number(name: &str, default: u64) -> u64 {
+    match env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|_| panic!("{name} is not a number")),
+        Err(_) => default,
+    }
+}
+
+fn env_duration(name: &str, default_seconds: u64) -> Duration {
+    Duration::from_secs(env_number(name, default_seconds))
+}
+
+async fn shutdown(
+    tx: CommandSender,
+    announcements: broadcast::Sender<ServerMessage>,
+    shutting_down: Arc<AtomicBool>,
+    drain_period: Duration,
+) {
+    let ctrl_c = async {
+        signal::ctrl_c().await.expect("Didnt gracfully shut down");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Unable to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Shutting down because of CTRL+C"),
+        _ = terminate => tracing::info!("Shutting down because of SIGTERM"),
+    }
+
+    shutting_down.store(true, Ordering::SeqCst);
+    if let Err(err) = tx.send(GameCommand::Shutdown).await {
+        tracing::error!("Unable to send message to game coordinator {err}");
+    }
+    let shutdown_message = ServerMessage::ServerShuttingDown {
+        reconnect_after: drain_period.as_secs(),
+    };
+    if announcements.send(shutdown_message).is_err() {
+        info!("No connected players to notify");
+    }
+
+    tracing::info!("Draining games for {}s", drain_period.as_secs());
+    tokio::time::sleep(drain_period).await;
+
+    let (done, mut closed) = mpsc::channel(1);
+    let close_all = GameCommand::CloseAll {
+        reason: "Server restarted".to_string(),
+        done,
+    };
+    if let Err(err) = tx.send(close_all).await {
+        tracing::error!("Unable to send message to game coordinator {err}");
+        return;
+    }
+    // The coordinator answers once every room has told its players it closed.
+    closed.recv().await;
 }
 
 async fn ws_handler(
     ws: WebSocketUpgrade,
     _validated_origin: ValidOrigin,
     State(state): State<AppState>,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, state.tx))
+    Query(params): Query<SocketParams>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    if state.shutting_down.load(Ordering::SeqCst) {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    // Signed in players are bound to their account for the whole connection.
+    let account = match auth::bearer_token(&headers, params.token.as_deref()) {
+        Some(token) => Some(state.user_tokens.verify(token).ok_or(StatusCode::UNAUTHORIZED)?),
+        None => None,
+    };
+    Ok(ws.protocols(protocol::SUBPROTOCOLS).on_upgrade(move |socket| {
+        handle_socket(
+            socket,
+            state.tx,
+            state.announcements.subscribe(),
+            SocketContext {
+                resume_tokens: state.resume_tokens,
+                user_tokens: state.user_tokens,
+                storage: state.storage,
+                leaderboards: state.leaderboards,
+            },
+            account,
+        )
+    }))
+}
+
+async fn rooms_handler(State(state): State<AppState>) -> Result<Json<Vec<RoomSummary>>, StatusCode> {
+    let (reply_sender, mut reply) = mpsc::channel(1);
+    let reply_sender = ReplySender::new(reply_sender, None);
+    state
+        .tx
+        .send(GameCommand::ListRooms { reply_sender })
+        .await
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+
+    match reply.recv().await {
+        Some(ServerMessage::RoomList { rooms }) => Ok(Json(rooms)),
+        _ => Err(StatusCode::SERVICE_UNAVAILABLE),
+    }
+}
+
+/// Players that haven't finished a round yet get empty stats, the same as `GetStats` over the websocket.
+async fn player_stats_handler(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Json<PlayerStats>, StatusCode> {
+    match state.storage.load_player_stats(&user_id).await {
+        Ok(stats) => Ok(Json(stats.unwrap_or_default())),
+        Err(err) => {
+            error!("Failed to load player stats: {err}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn leaderboard_handler(
+    State(state): State<AppState>,
+    Path(board): Path<LeaderboardKind>,
+    Query(params): Query<LeaderboardParams>,
+) -> Result<Json<Vec<LeaderboardEntry>>, StatusCode> {
+    let query = LeaderboardQuery {
+        board,
+        window: params.window,
+        mode: params.mode,
+    };
+    state.leaderboards.get(query).await.map(Json).map_err(|err| {
+        error!("Failed to load leaderboard: {err}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
 }
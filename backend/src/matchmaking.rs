@@ -0,0 +1,309 @@
+use crate::game::{GameMode, GameSettings, PlayerId, PublicPlayer, ReplySender, UserId};
+use crate::storage::{SharedStorage, StorageError};
+use std::collections::VecDeque;
+use tokio::time::{Duration, Instant};
+
+pub const DEFAULT_RATING: f64 = 1200.0;
+const ELO_K_FACTOR: f64 = 32.0;
+/// Competitive players start out only matched within this many points of each other...
+const BASE_RATING_WINDOW: f64 = 100.0;
+/// ...and accept wider gaps the longer they wait.
+const RATING_WINDOW_PER_SECOND: f64 = 20.0;
+
+/// Elo update for a single game, `score` is 1.0 when `rating` won, 0.5 for a draw and 0.0 for a loss.
+pub fn elo_update(rating: f64, opponent: f64, score: f64) -> (f64, f64) {
+    let expected = 1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0));
+    let change = ELO_K_FACTOR * (score - expected);
+    (rating + change, opponent - change)
+}
+
+/// Players nobody has rated yet start from [`DEFAULT_RATING`].
+pub async fn load_rating(storage: &SharedStorage, user_id: &str) -> Result<f64, StorageError> {
+    Ok(storage.load_rating(user_id).await?.unwrap_or(DEFAULT_RATING))
+}
+
+/// Applies the result of a competitive round, `score` is from `first`'s point of view.
+pub async fn update_ratings(
+    storage: &SharedStorage,
+    first: &str,
+    second: &str,
+    score: f64,
+) -> Result<(), StorageError> {
+    let (first_rating, second_rating) = elo_update(
+        load_rating(storage, first).await?,
+        load_rating(storage, second).await?,
+        score,
+    );
+    storage.save_rating(first, first_rating).await?;
+    storage.save_rating(second, second_rating).await
+}
+
+/// A player waiting for a quick match partner.
+#[derive(Debug, Clone)]
+pub struct MatchTicket {
+    pub player_id: PlayerId,
+    pub user_id: UserId,
+    pub profile: PublicPlayer,
+    pub settings: GameSettings,
+    pub rating: f64,
+    pub reply_sender: ReplySender,
+    pub queued_at: Instant,
+}
+
+impl MatchTicket {
+    fn rating_window(&self, now: Instant) -> f64 {
+        BASE_RATING_WINDOW + RATING_WINDOW_PER_SECOND * now.duration_since(self.queued_at).as_secs_f64()
+    }
+
+    fn can_play(&self, other: &MatchTicket, now: Instant) -> bool {
+        if self.settings != other.settings || self.user_id == other.user_id {
+            return false;
+        }
+        if self.settings.mode != GameMode::Competitive {
+            return true;
+        }
+        let window = self.rating_window(now).max(other.rating_window(now));
+        (self.rating - other.rating).abs() <= window
+    }
+}
+
+/// Quick match queue owned by the coordinator, oldest ticket first.
+#[derive(Debug)]
+pub struct MatchQueue {
+    tickets: VecDeque<MatchTicket>,
+    timeout: Duration,
+}
+
+impl MatchQueue {
+    pub fn new(timeout: Duration) -> Self {
+        MatchQueue {
+            tickets: VecDeque::new(),
+            timeout,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tickets.is_empty()
+    }
+
+    /// Pairs the ticket with the closest rated compatible player, or queues it when there is none.
+    pub fn enqueue(&mut self, ticket: MatchTicket) -> Option<(MatchTicket, MatchTicket)> {
+        self.remove(&ticket.player_id);
+        self.tickets.retain(|waiting| !waiting.reply_sender.is_closed());
+
+        let now = Instant::now();
+        let closest = self
+            .tickets
+            .iter()
+            .enumerate()
+            .filter(|(_, waiting)| waiting.can_play(&ticket, now))
+            .min_by(|(_, a), (_, b)| {
+                let gap_a = (a.rating - ticket.rating).abs();
+                let gap_b = (b.rating - ticket.rating).abs();
+                gap_a.total_cmp(&gap_b)
+            })
+            .map(|(index, _)| index);
+
+        match closest {
+            Some(index) => self.tickets.remove(index).map(|waiting| (waiting, ticket)),
+            None => {
+                self.tickets.push_back(ticket);
+                None
+            }
+        }
+    }
+
+    /// Pairs up players that were too far apart when they queued but whose windows have widened since.
+    pub fn take_widened_pair(&mut self) -> Option<(MatchTicket, MatchTicket)> {
+        let now = Instant::now();
+        let (first, second) = self.tickets.iter().enumerate().find_map(|(i, ticket)| {
+            self.tickets
+                .iter()
+                .enumerate()
+                .skip(i + 1)
+                .find(|(_, other)| ticket.can_play(other, now))
+                .map(|(j, _)| (i, j))
+        })?;
+        let second = self.tickets.remove(second)?;
+        let first = self.tickets.remove(first)?;
+        Some((first, second))
+    }
+
+    pub fn remove(&mut self, player_id: &str) -> Option<MatchTicket> {
+        let index = self.tickets.iter().position(|ticket| ticket.player_id == player_id)?;
+        self.tickets.remove(index)
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.tickets.front().map(|ticket| ticket.queued_at + self.timeout)
+    }
+
+    pub fn expire(&mut self) -> Vec<MatchTicket> {
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        while let Some(ticket) = self.tickets.front()
+            && ticket.queued_at + self.timeout <= now
+        {
+            expired.extend(self.tickets.pop_front());
+        }
+        expired
+    }
+
+    /// Every waiting player with their place among those looking for the same settings, starting at 1.
+    pub fn positions(&self) -> Vec<(ReplySender, usize)> {
+        self.tickets
+            .iter()
+            .enumerate()
+            .map(|(index, ticket)| {
+                let ahead = self
+                    .tickets
+                    .iter()
+                    .take(index)
+                    .filter(|waiting| waiting.settings == ticket.settings)
+                    .count();
+                (ticket.reply_sender.clone(), ahead + 1)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameMode, ServerMessage};
+    use crate::storage::MemoryStorage;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    fn ticket(player_id: &str, settings: GameSettings) -> (MatchTicket, mpsc::Receiver<ServerMessage>) {
+        let (reply_sender, reply_rx) = mpsc::channel(8);
+        let ticket = MatchTicket {
+            player_id: player_id.to_string(),
+            user_id: player_id.to_string(),
+            profile: PublicPlayer::new(player_id.to_string(), None, None).unwrap(),
+            settings,
+            rating: DEFAULT_RATING,
+            reply_sender: ReplySender::new(reply_sender, None),
+            queued_at: Instant::now(),
+        };
+        (ticket, reply_rx)
+    }
+
+    fn competitive() -> GameSettings {
+        GameSettings {
+            mode: GameMode::Competitive,
+            ..GameSettings::default()
+        }
+    }
+
+    #[test]
+    fn only_players_after_the_same_settings_are_paired() {
+        let mut queue = MatchQueue::new(Duration::from_secs(60));
+        let (first, _first_rx) = ticket("p1", GameSettings::default());
+        let (second, _second_rx) = ticket("p2", competitive());
+        let (third, _third_rx) = ticket("p3", GameSettings::default());
+
+        assert!(queue.enqueue(first).is_none());
+        assert!(queue.enqueue(second).is_none());
+        let (host, guest) = queue.enqueue(third).unwrap();
+        assert_eq!((host.player_id.as_str(), guest.player_id.as_str()), ("p1", "p3"));
+        assert_eq!(queue.positions().len(), 1);
+    }
+
+    #[test]
+    fn players_who_left_are_not_matched() {
+        let mut queue = MatchQueue::new(Duration::from_secs(60));
+        let (first, first_rx) = ticket("p1", GameSettings::default());
+        let (second, _second_rx) = ticket("p2", GameSettings::default());
+        queue.enqueue(first);
+        drop(first_rx);
+
+        assert!(queue.enqueue(second).is_none());
+        assert_eq!(queue.positions().len(), 1);
+        assert!(queue.remove("p2").is_some());
+        assert!(queue.remove("p2").is_none());
+    }
+
+    #[test]
+    fn positions_count_players_after_the_same_settings() {
+        let mut queue = MatchQueue::new(Duration::from_secs(60));
+        let (first, _first_rx) = ticket("p1", GameSettings::default());
+        let (second, _second_rx) = ticket("p2", competitive());
+        queue.enqueue(first);
+        queue.enqueue(second);
+
+        let positions: Vec<usize> = queue.positions().into_iter().map(|(_, position)| position).collect();
+        assert_eq!(positions, vec![1, 1]);
+    }
+
+    #[tokio::test]
+    async fn tickets_expire_after_the_timeout() {
+        let mut queue = MatchQueue::new(Duration::ZERO);
+        let (first, _first_rx) = ticket("p1", GameSettings::default());
+        queue.enqueue(first);
+        assert!(queue.next_deadline().is_some());
+
+        let expired = queue.expire();
+        assert_eq!(expired.len(), 1);
+        assert!(queue.next_deadline().is_none());
+    }
+
+    fn rated(player_id: &str, rating: f64, waited: Duration) -> (MatchTicket, mpsc::Receiver<ServerMessage>) {
+        let (mut ticket, reply_rx) = ticket(player_id, competitive());
+        ticket.rating = rating;
+        ticket.queued_at = Instant::now() - waited;
+        (ticket, reply_rx)
+    }
+
+    #[test]
+    fn elo_moves_ratings_by_the_same_amount() {
+        let (winner, loser) = elo_update(DEFAULT_RATING, DEFAULT_RATING, 1.0);
+        assert_eq!(winner, DEFAULT_RATING + ELO_K_FACTOR / 2.0);
+        assert_eq!(loser, DEFAULT_RATING - ELO_K_FACTOR / 2.0);
+
+        let (favourite, underdog) = elo_update(1600.0, 1200.0, 0.5);
+        assert!(favourite < 1600.0 && underdog > 1200.0);
+        assert_eq!(favourite + underdog, 2800.0);
+    }
+
+    #[tokio::test]
+    async fn rating_window_widens_while_waiting() {
+        let mut queue = MatchQueue::new(Duration::from_secs(120));
+        let (first, _first_rx) = rated("first", 1200.0, Duration::ZERO);
+        let (second, _second_rx) = rated("second", 1400.0, Duration::ZERO);
+        assert!(queue.enqueue(first).is_none());
+        assert!(queue.enqueue(second).is_none());
+        assert!(queue.take_widened_pair().is_none());
+
+        let mut queue = MatchQueue::new(Duration::from_secs(120));
+        let (patient, _patient_rx) = rated("patient", 1200.0, Duration::from_secs(10));
+        let (newcomer, _newcomer_rx) = rated("newcomer", 1400.0, Duration::ZERO);
+        assert!(queue.enqueue(patient).is_none());
+        let (host, guest) = queue
+            .enqueue(newcomer)
+            .expect("Window should have widened to 300 points");
+        assert_eq!(
+            (host.player_id.as_str(), guest.player_id.as_str()),
+            ("patient", "newcomer")
+        );
+    }
+
+    #[test]
+    fn players_are_not_matched_against_themselves() {
+        let mut queue = MatchQueue::new(Duration::from_secs(60));
+        let (first, _first_rx) = ticket("p1", GameSettings::default());
+        let (mut second, _second_rx) = ticket("p2", GameSettings::default());
+        second.user_id = first.user_id.clone();
+        assert!(queue.enqueue(first).is_none());
+        assert!(queue.enqueue(second).is_none());
+    }
+
+    #[tokio::test]
+    async fn ratings_are_saved_for_both_players() {
+        let memory = Arc::new(MemoryStorage::new());
+        let storage: SharedStorage = memory.clone();
+        update_ratings(&storage, "first", "second", 1.0).await.unwrap();
+        memory.assert_rating("first", DEFAULT_RATING + ELO_K_FACTOR / 2.0);
+        memory.assert_rating("second", DEFAULT_RATING - ELO_K_FACTOR / 2.0);
+    }
+}
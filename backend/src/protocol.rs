@@ -0,0 +1,152 @@
+use crate::game::GameError;
+use axum::{extract::ws::Message, http::HeaderValue};
+use serde::Serialize;
+
+/// Bumped whenever the JSON shape of a client or server message changes incompatibly.
+pub const PROTOCOL_VERSION: u32 = 2;
+/// Oldest client protocol the server still speaks.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+/// First version sent a `BoardDelta` after a guess, older clients get the full board instead.
+pub const BOARD_DELTA_VERSION: u32 = 2;
+/// Clients built before versioning never send one, they speak the first version.
+const UNVERSIONED_PROTOCOL_VERSION: u32 = 1;
+
+/// Optional parts of the protocol, advertised in `Welcome` so clients can hide what the server doesn't offer.
+const FEATURES: &[&str] = &[
+    "acks",
+    "chat",
+    "drafts",
+    "leaderboards",
+    "matchmaking",
+    "reactions",
+    "stats",
+    "suggestions",
+];
+
+/// The features a client speaking `version` gets.
+pub fn features(version: u32) -> Vec<String> {
+    let mut features: Vec<String> = FEATURES.iter().map(|feature| feature.to_string()).collect();
+    if version >= BOARD_DELTA_VERSION {
+        features.push("boardDeltas".to_string());
+    }
+    features
+}
+
+/// Picks the version to speak with a client, or explains why there is none.
+///
+/// Clients newer than the server are talked to in the server's latest version, they are expected to still speak it.
+pub fn negotiate(client_version: Option<u32>) -> Result<u32, GameError> {
+    let version = client_version.unwrap_or(UNVERSIONED_PROTOCOL_VERSION);
+    if version < MIN_PROTOCOL_VERSION {
+        return Err(GameError::UnsupportedProtocol {
+            message: format!(
+                "Protocol version {version} is no longer supported, this server needs {MIN_PROTOCOL_VERSION} or later"
+            ),
+            min_version: MIN_PROTOCOL_VERSION,
+            max_version: PROTOCOL_VERSION,
+        });
+    }
+    Ok(version.min(PROTOCOL_VERSION))
+}
+
+pub const JSON_SUBPROTOCOL: &str = "wordle.json";
+pub const MSGPACK_SUBPROTOCOL: &str = "wordle.msgpack";
+/// Offered during the websocket handshake, in order of preference.
+pub const SUBPROTOCOLS: [&str; 2] = [MSGPACK_SUBPROTOCOL, JSON_SUBPROTOCOL];
+
+/// How server messages are written to a socket, picked once through the websocket subprotocol.
+///
+/// Clients may send either JSON text or MessagePack binary frames whatever they picked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+impl Encoding {
+    pub fn negotiated(subprotocol: Option<&HeaderValue>) -> Self {
+        match subprotocol.and_then(|value| value.to_str().ok()) {
+            Some(MSGPACK_SUBPROTOCOL) => Encoding::MessagePack,
+            _ => Encoding::Json,
+        }
+    }
+
+    pub fn encode<T: Serialize>(self, value: &T) -> Option<Message> {
+        match self {
+            Encoding::Json => serde_json::to_string(value).ok().map(|text| Message::Text(text.into())),
+            // Named fields keep tagged and flattened enums readable by the same serde types.
+            Encoding::MessagePack => rmp_serde::to_vec_named(value)
+                .ok()
+                .map(|bytes| Message::Binary(bytes.into())),
+        }
+    }
+}
+
+/// Reads a client frame in whichever encoding it came in, text frames are JSON and binary ones MessagePack.
+pub fn decode(message: &Message) -> Option<serde_json::Value> {
+    match message {
+        Message::Text(text) => serde_json::from_str(text.as_str()).ok(),
+        Message::Binary(bytes) => rmp_serde::from_slice(bytes).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clients_older_than_the_minimum_are_turned_away() {
+        assert!(matches!(
+            negotiate(Some(MIN_PROTOCOL_VERSION - 1)),
+            Err(GameError::UnsupportedProtocol {
+                min_version: MIN_PROTOCOL_VERSION,
+                max_version: PROTOCOL_VERSION,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn supported_clients_keep_their_version() {
+        assert_eq!(negotiate(Some(MIN_PROTOCOL_VERSION)).unwrap(), MIN_PROTOCOL_VERSION);
+        assert_eq!(negotiate(Some(PROTOCOL_VERSION)).unwrap(), PROTOCOL_VERSION);
+        assert_eq!(negotiate(None).unwrap(), UNVERSIONED_PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn newer_clients_are_spoken_to_in_the_latest_version() {
+        assert_eq!(negotiate(Some(PROTOCOL_VERSION + 1)).unwrap(), PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn only_clients_that_speak_deltas_are_offered_them() {
+        assert!(!features(BOARD_DELTA_VERSION - 1).contains(&"boardDeltas".to_string()));
+        assert!(features(BOARD_DELTA_VERSION).contains(&"boardDeltas".to_string()));
+    }
+
+    #[test]
+    fn the_msgpack_subprotocol_picks_messagepack() {
+        assert_eq!(
+            Encoding::negotiated(Some(&HeaderValue::from_static(MSGPACK_SUBPROTOCOL))),
+            Encoding::MessagePack
+        );
+        assert_eq!(
+            Encoding::negotiated(Some(&HeaderValue::from_static(JSON_SUBPROTOCOL))),
+            Encoding::Json
+        );
+        assert_eq!(Encoding::negotiated(None), Encoding::Json);
+    }
+
+    #[test]
+    fn both_encodings_round_trip() {
+        let value = serde_json::json!({ "status": "ack", "requestId": "1", "nested": { "list": [1, 2] } });
+        for encoding in [Encoding::Json, Encoding::MessagePack] {
+            let message = encoding.encode(&value).unwrap();
+            assert_eq!(matches!(message, Message::Binary(_)), encoding == Encoding::MessagePack);
+            assert_eq!(decode(&message), Some(value.clone()));
+        }
+        assert_eq!(decode(&Message::Binary(vec![0xc1].into())), None);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::chat::ChatEntry;
+use crate::game::ServerMessage;
+use crate::protocol::PROTOCOL_VERSION;
+use crate::websocket::{ClientMessage, ClientRequest};
+use schemars::{JsonSchema, schema_for};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+use ts_rs::{TS, TypeVisitor};
+
+const TYPESCRIPT_PATH: &str = "../frontend/src/types/protocol.ts";
+const CLIENT_SCHEMA_PATH: &str = "schema/clientRequest.schema.json";
+const SERVER_SCHEMA_PATH: &str = "schema/serverMessage.schema.json";
+
+const TYPESCRIPT_HEADER: &str =
+    "// Generated from the backend protocol types by `cargo run -- protocol-types`, do not edit by hand.\n";
+
+/// Collects the declaration of every named type reachable from the visited ones.
+#[derive(Default)]
+struct Declarations(BTreeMap<String, String>);
+
+impl TypeVisitor for Declarations {
+    fn visit<T: TS + 'static + ?Sized>(&mut self) {
+        // Only derived types are declared, built-ins like `String` or `Vec` are inlined.
+        if T::output_path().is_none() || self.0.contains_key(&T::ident()) {
+            return;
+        }
+        let docs = T::docs().unwrap_or_default();
+        self.0.insert(T::ident(), format!("{docs}export {}", T::decl()));
+        T::visit_dependencies(self);
+    }
+}
+
+fn typescript() -> String {
+    let mut declarations = Declarations::default();
+    declarations.visit::<ClientRequest>();
+    declarations.visit::<ServerMessage>();
+    // Flattened into the messages above, but the frontend also handles them on their own.
+    declarations.visit::<ClientMessage>();
+    declarations.visit::<ChatEntry>();
+    let mut body: Vec<String> = declarations.0.into_values().collect();
+    // Sent in `connect`, so the frontend always speaks the version its types were generated for.
+    body.push(format!("export const PROTOCOL_VERSION = {PROTOCOL_VERSION};"));
+    format!("{TYPESCRIPT_HEADER}\n{}\n", body.join("\n\n"))
+}
+
+fn json_schema<T: JsonSchema>() -> String {
+    let schema = serde_json::to_string_pretty(&schema_for!(T)).expect("Schemas always serialize");
+    format!("{schema}\n")
+}
+
+fn generated_files() -> Vec<(PathBuf, String)> {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    vec![
+        (root.join(TYPESCRIPT_PATH), typescript()),
+        (root.join(CLIENT_SCHEMA_PATH), json_schema::<ClientRequest>()),
+        (root.join(SERVER_SCHEMA_PATH), json_schema::<ServerMessage>()),
+    ]
+}
+
+/// Writes the TypeScript definitions and JSON Schemas for the websocket protocol, or with `check` only reports
+/// the checked-in copies that no longer match the Rust types. Returns whether everything is up to date.
+pub fn write_protocol_types(check: bool) -> bool {
+    let mut stale = false;
+    for (path, contents) in generated_files() {
+        if fs::read_to_string(&path).is_ok_and(|current| current == contents) {
+            continue;
+        }
+        if check {
+            eprintln!("{} is stale, run `cargo run -- protocol-types`", path.display());
+            stale = true;
+            continue;
+        }
+        if let Some(parent) = path.parent()
+            && let Err(err) = fs::create_dir_all(parent)
+        {
+            eprintln!("Unable to create {}: {err}", parent.display());
+            return false;
+        }
+        if let Err(err) = fs::write(&path, contents) {
+            eprintln!("Unable to write {}: {err}", path.display());
+            return false;
+        }
+        println!("Wrote {}", path.display());
+    }
+    !stale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_in_protocol_types_are_up_to_date() {
+        for (path, contents) in generated_files() {
+            let current = fs::read_to_string(&path).unwrap_or_default();
+            assert!(
+                current == contents,
+                "{} is stale, run `cargo run -- protocol-types`",
+                path.display()
+            );
+        }
+    }
+}
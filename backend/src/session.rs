@@ -0,0 +1,314 @@
+use crate::game::{GameError, GameId, PlayerHandle, PlayerId, UserId};
+use crate::storage;
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::SaltString};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use rand::{Rng, distr::Alphanumeric, rng};
+use sha2::Sha256;
+use std::{fmt, sync::Arc, time::Duration};
+use tokio::task;
+use tracing::error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const HANDLE_LENGTH: usize = 8;
+const MAX_PASSWORD_LENGTH: usize = 64;
+
+pub fn random_handle() -> PlayerHandle {
+    rng()
+        .sample_iter(&Alphanumeric)
+        .take(HANDLE_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Hashes the password a room is created with, a blank one means the room is open.
+pub async fn hash_room_password(password: Option<String>) -> Result<Option<String>, GameError> {
+    let Some(password) = password.filter(|password| !password.trim().is_empty()) else {
+        return Ok(None);
+    };
+    if password.chars().count() > MAX_PASSWORD_LENGTH {
+        return Err(GameError::JoinError {
+            message: format!("Password should be at most {MAX_PASSWORD_LENGTH} characters long"),
+        });
+    }
+    match hash_password(password).await {
+        Some(hash) => Ok(Some(hash)),
+        None => Err(GameError::JoinError {
+            message: "Unable to set room password".to_string(),
+        }),
+    }
+}
+
+/// Argon2 is deliberately slow, so hashing and verifying run on the blocking pool.
+pub async fn hash_password(password: String) -> Option<String> {
+    let hashed = task::spawn_blocking(move || {
+        let mut salt = [0u8; 16];
+        rng().fill(&mut salt);
+        let salt = SaltString::encode_b64(&salt)?;
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+    })
+    .await;
+
+    match hashed {
+        Ok(Ok(hash)) => Some(hash),
+        Ok(Err(err)) => {
+            error!("Failed to hash password: {err}");
+            None
+        }
+        Err(err) => {
+            error!("Password hashing task failed: {err}");
+            None
+        }
+    }
+}
+
+pub async fn verify_password(password: String, hash: String) -> bool {
+    task::spawn_blocking(move || {
+        PasswordHash::new(&hash).is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+    })
+    .await
+    .unwrap_or(false)
+}
+
+/// HMAC signs the tokens handed to clients so they can't be forged or edited.
+#[derive(Clone)]
+pub struct TokenSigner {
+    secret: Arc<[u8]>,
+}
+
+impl fmt::Debug for TokenSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenSigner").finish_non_exhaustive()
+    }
+}
+
+impl TokenSigner {
+    pub fn new(secret: Option<String>) -> Self {
+        let secret: Arc<[u8]> = match secret {
+            Some(secret) => secret.into_bytes().into(),
+            None => {
+                let mut secret = [0u8; 32];
+                rng().fill(&mut secret);
+                secret.into()
+            }
+        };
+        TokenSigner { secret }
+    }
+
+    fn sign(&self, payload: &str) -> String {
+        let signature = self.mac(payload.as_bytes()).finalize().into_bytes();
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(payload),
+            URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    /// Returns the signed payload if the token is genuine.
+    fn open(&self, token: &str) -> Option<String> {
+        let (payload, signature) = token.split_once('.')?;
+        let payload = URL_SAFE_NO_PAD.decode(payload).ok()?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        self.mac(&payload).verify_slice(&signature).ok()?;
+        String::from_utf8(payload).ok()
+    }
+
+    fn mac(&self, payload: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(payload);
+        mac
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResumedSeat {
+    pub player_id: PlayerId,
+    pub handle: PlayerHandle,
+}
+
+/// Issues and verifies the signed tokens a client must present to resume its seat in a game.
+#[derive(Debug, Clone)]
+pub struct ResumeTokens {
+    signer: TokenSigner,
+    ttl: Duration,
+}
+
+impl ResumeTokens {
+    pub fn new(signer: TokenSigner, ttl: Duration) -> Self {
+        ResumeTokens { signer, ttl }
+    }
+
+    pub fn issue(&self, player_id: &str, handle: &str, game_id: &str) -> String {
+        let expires_at = storage::now_secs() + self.ttl.as_secs();
+        self.signer
+            .sign(&format!("{player_id}|{handle}|{game_id}|{expires_at}"))
+    }
+
+    /// Returns the seat the token was issued for, as long as it is genuine, unexpired and bound to `game_id`.
+    pub fn verify(&self, token: &str, game_id: &GameId) -> Result<ResumedSeat, GameError> {
+        let invalid = || GameError::InvalidResumeToken {
+            message: "Resume token is invalid".to_string(),
+        };
+
+        let payload = self.signer.open(token).ok_or_else(invalid)?;
+        let mut parts = payload.split('|');
+        let (Some(player_id), Some(handle), Some(token_game_id), Some(expires_at), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        if token_game_id != game_id {
+            return Err(invalid());
+        }
+        let expires_at: u64 = expires_at.parse().map_err(|_| invalid())?;
+        if expires_at < storage::now_secs() {
+            return Err(GameError::ExpiredResumeToken {
+                message: "Resume token has expired".to_string(),
+            });
+        }
+        Ok(ResumedSeat {
+            player_id: player_id.to_string(),
+            handle: handle.to_string(),
+        })
+    }
+}
+
+/// Issues and verifies the long lived tokens that tie a client to the same user id across connections.
+#[derive(Debug, Clone)]
+pub struct UserTokens {
+    signer: TokenSigner,
+    ttl: Duration,
+}
+
+impl UserTokens {
+    pub fn new(signer: TokenSigner, ttl: Duration) -> Self {
+        UserTokens { signer, ttl }
+    }
+
+    pub fn issue(&self, user_id: &str) -> String {
+        let expires_at = storage::now_secs() + self.ttl.as_secs();
+        self.signer.sign(&format!("user|{user_id}|{expires_at}"))
+    }
+
+    pub fn verify(&self, token: &str) -> Option<UserId> {
+        let payload = self.signer.open(token)?;
+        let mut parts = payload.split('|');
+        let (Some("user"), Some(user_id), Some(expires_at), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return None;
+        };
+        let expires_at: u64 = expires_at.parse().ok()?;
+        (expires_at >= storage::now_secs()).then(|| user_id.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resume_tokens(secret: &str) -> ResumeTokens {
+        ResumeTokens::new(TokenSigner::new(Some(secret.to_string())), Duration::from_secs(60))
+    }
+
+    #[test]
+    fn resume_tokens_verify_for_their_game() {
+        let tokens = resume_tokens("secret");
+        let token = tokens.issue("player", "handle", "GAME");
+        let seat = tokens.verify(&token, &"GAME".to_string()).unwrap();
+        assert_eq!((seat.player_id.as_str(), seat.handle.as_str()), ("player", "handle"));
+    }
+
+    #[test]
+    fn resume_tokens_are_bound_to_their_game() {
+        let tokens = resume_tokens("secret");
+        let token = tokens.issue("player", "handle", "GAME");
+        assert!(matches!(
+            tokens.verify(&token, &"OTHER".to_string()),
+            Err(GameError::InvalidResumeToken { .. })
+        ));
+    }
+
+    #[test]
+    fn forged_resume_tokens_are_rejected() {
+        let token = resume_tokens("attacker").issue("player", "handle", "GAME");
+        assert!(matches!(
+            resume_tokens("secret").verify(&token, &"GAME".to_string()),
+            Err(GameError::InvalidResumeToken { .. })
+        ));
+        assert!(matches!(
+            resume_tokens("secret").verify("not-a-token", &"GAME".to_string()),
+            Err(GameError::InvalidResumeToken { .. })
+        ));
+    }
+
+    #[test]
+    fn tampered_resume_tokens_are_rejected() {
+        let tokens = resume_tokens("secret");
+        let token = tokens.issue("player", "handle", "GAME");
+        let (_, signature) = token.split_once('.').unwrap();
+        let expires_at = storage::now_secs() + 60;
+        let payload = URL_SAFE_NO_PAD.encode(format!("victim|handle|GAME|{expires_at}"));
+        assert!(matches!(
+            tokens.verify(&format!("{payload}.{signature}"), &"GAME".to_string()),
+            Err(GameError::InvalidResumeToken { .. })
+        ));
+    }
+
+    #[test]
+    fn expired_resume_tokens_are_rejected() {
+        let signer = TokenSigner::new(Some("secret".to_string()));
+        let tokens = ResumeTokens::new(signer.clone(), Duration::ZERO);
+        let token = signer.sign(&format!("player|handle|GAME|{}", storage::now_secs() - 1));
+        assert!(matches!(
+            tokens.verify(&token, &"GAME".to_string()),
+            Err(GameError::ExpiredResumeToken { .. })
+        ));
+    }
+
+    #[test]
+    fn user_tokens_round_trip() {
+        let signer = TokenSigner::new(Some("secret".to_string()));
+        let tokens = UserTokens::new(signer.clone(), Duration::from_secs(60));
+        let token = tokens.issue("user");
+        assert_eq!(tokens.verify(&token).as_deref(), Some("user"));
+
+        let resume_token = ResumeTokens::new(signer, Duration::from_secs(60)).issue("user", "handle", "GAME");
+        assert_eq!(tokens.verify(&resume_token), None);
+        assert_eq!(
+            UserTokens::new(TokenSigner::new(Some("other".to_string())), Duration::from_secs(60)).verify(&token),
+            None
+        );
+    }
+
+    #[test]
+    fn tampered_and_expired_user_tokens_are_rejected() {
+        let signer = TokenSigner::new(Some("secret".to_string()));
+        let tokens = UserTokens::new(signer.clone(), Duration::from_secs(60));
+        let token = tokens.issue("user");
+        let (_, signature) = token.split_once('.').unwrap();
+        let payload = URL_SAFE_NO_PAD.encode(format!("user|admin|{}", storage::now_secs() + 60));
+        assert_eq!(tokens.verify(&format!("{payload}.{signature}")), None);
+
+        let expired = signer.sign(&format!("user|user|{}", storage::now_secs() - 1));
+        assert_eq!(tokens.verify(&expired), None);
+    }
+
+    #[tokio::test]
+    async fn room_passwords_are_hashed_and_verified() {
+        assert!(hash_room_password(None).await.unwrap().is_none());
+        assert!(hash_room_password(Some("  ".to_string())).await.unwrap().is_none());
+        assert!(matches!(
+            hash_room_password(Some("x".repeat(MAX_PASSWORD_LENGTH + 1))).await,
+            Err(GameError::JoinError { .. })
+        ));
+
+        let hash = hash_room_password(Some("hunter2".to_string())).await.unwrap().unwrap();
+        assert_ne!(hash, "hunter2");
+        assert!(verify_password("hunter2".to_string(), hash.clone()).await);
+        assert!(!verify_password("hunter3".to_string(), hash).await);
+    }
+}
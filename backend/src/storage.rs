@@ -0,0 +1,243 @@
+mod memory;
+mod sqlite;
+
+use crate::game::{BoardState, GameId, GameMode, GameStatus, MAX_GUESSES, UserId};
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+use ts_rs::TS;
+
+pub use memory::MemoryStorage;
+pub use sqlite::SqliteStorage;
+
+pub type SharedStorage = Arc<dyn Storage>;
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("unable to encode record: {0}")]
+    Encoding(#[from] serde_json::Error),
+    #[error("storage task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GameRecord {
+    pub game_id: GameId,
+    pub solution_word: String,
+    pub board_state: BoardState,
+    #[serde(default)]
+    pub password_hash: Option<String>,
+    pub updated_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundRecord {
+    pub game_id: GameId,
+    pub solution_word: String,
+    pub guesses: Vec<String>,
+    pub players: Vec<UserId>,
+    pub winners: Vec<UserId>,
+    pub mode: GameMode,
+    pub game_status: GameStatus,
+    pub finished_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountRecord {
+    pub user_id: UserId,
+    pub username: String,
+    pub password_hash: String,
+    pub created_at: u64,
+}
+
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerStats {
+    pub games_played: u32,
+    pub games_won: u32,
+    pub win_percentage: u32,
+    pub current_streak: u32,
+    pub max_streak: u32,
+    /// How many rounds were won in 1, 2, ... `MAX_GUESSES` guesses.
+    pub guess_distribution: Vec<u32>,
+    /// The last daily puzzle that counted, playing it again in another room doesn't.
+    #[serde(skip)]
+    pub last_daily_word: Option<String>,
+}
+
+impl Default for PlayerStats {
+    fn default() -> Self {
+        PlayerStats {
+            games_played: 0,
+            games_won: 0,
+            win_percentage: 0,
+            current_streak: 0,
+            max_streak: 0,
+            guess_distribution: vec![0; MAX_GUESSES],
+            last_daily_word: None,
+        }
+    }
+}
+
+impl PlayerStats {
+    pub fn record(&mut self, won: bool, guesses: usize) {
+        self.games_played += 1;
+        if won {
+            self.games_won += 1;
+            self.current_streak += 1;
+            self.max_streak = self.max_streak.max(self.current_streak);
+            self.guess_distribution.resize(MAX_GUESSES, 0);
+            if let Some(count) = guesses
+                .checked_sub(1)
+                .and_then(|index| self.guess_distribution.get_mut(index))
+            {
+                *count += 1;
+            }
+        } else {
+            self.current_streak = 0;
+        }
+        self.win_percentage = self.games_won * 100 / self.games_played;
+    }
+
+    /// Adds `user_id`'s result in `round`, returns `false` when it was a replay of the last daily puzzle.
+    pub fn record_round(&mut self, user_id: &str, round: &RoundRecord) -> bool {
+        if round.mode == GameMode::Daily {
+            if self.last_daily_word.as_ref() == Some(&round.solution_word) {
+                return false;
+            }
+            self.last_daily_word = Some(round.solution_word.clone());
+        }
+        let won = round.winners.iter().any(|winner| winner == user_id);
+        self.record(won, round.guesses.len());
+        true
+    }
+
+    /// Guesses it took across every won round, the distribution only keeps a count per guess number.
+    pub fn winning_guesses(&self) -> u32 {
+        self.guess_distribution
+            .iter()
+            .zip(1..)
+            .map(|(count, guesses)| count * guesses)
+            .sum()
+    }
+}
+
+/// Persistence backend shared by the coordinator and every game actor.
+///
+/// Callers never await these on the coordinator loop; writes are spawned onto their own tasks.
+#[async_trait]
+pub trait Storage: Send + Sync + Debug {
+    async fn save_game(&self, game: GameRecord) -> Result<(), StorageError>;
+    async fn load_game(&self, game_id: &str) -> Result<Option<GameRecord>, StorageError>;
+    /// Forgets a closed room so it can't be restored by its id again.
+    async fn delete_game(&self, game_id: &str) -> Result<(), StorageError>;
+    async fn append_round(&self, round: RoundRecord) -> Result<(), StorageError>;
+    /// Rounds finished at or after `since`, oldest first.
+    async fn load_rounds(&self, since: u64) -> Result<Vec<RoundRecord>, StorageError>;
+    /// Adds `round` to the player's overall stats and to their stats for the round's mode.
+    async fn record_player_stats(&self, user_id: &str, round: &RoundRecord) -> Result<(), StorageError>;
+    async fn load_player_stats(&self, user_id: &str) -> Result<Option<PlayerStats>, StorageError>;
+    /// Every player's all-time stats in `mode`, or across all modes when it is `None`.
+    async fn load_all_player_stats(&self, mode: Option<GameMode>)
+    -> Result<HashMap<UserId, PlayerStats>, StorageError>;
+    async fn load_rating(&self, user_id: &str) -> Result<Option<f64>, StorageError>;
+    async fn save_rating(&self, user_id: &str, rating: f64) -> Result<(), StorageError>;
+    async fn load_ratings(&self) -> Result<HashMap<UserId, f64>, StorageError>;
+    /// Returns `false` without touching the existing account when the username is already taken.
+    async fn create_account(&self, account: AccountRecord) -> Result<bool, StorageError>;
+    /// Usernames are matched case insensitively.
+    async fn load_account(&self, username: &str) -> Result<Option<AccountRecord>, StorageError>;
+    /// Account usernames for whichever of `user_ids` registered one.
+    async fn load_usernames(&self, user_ids: Vec<UserId>) -> Result<HashMap<UserId, String>, StorageError>;
+}
+
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Days since the epoch in UTC, what daily puzzles are keyed by.
+pub fn today() -> u64 {
+    now_secs() / SECONDS_PER_DAY
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wins_build_streaks_and_the_guess_distribution() {
+        let mut stats = PlayerStats::default();
+        stats.record(true, 3);
+        stats.record(true, 1);
+        stats.record(false, 6);
+        stats.record(true, 3);
+
+        assert_eq!(
+            stats,
+            PlayerStats {
+                games_played: 4,
+                games_won: 3,
+                win_percentage: 75,
+                current_streak: 1,
+                max_streak: 2,
+                guess_distribution: vec![1, 0, 2, 0, 0, 0],
+                last_daily_word: None,
+            }
+        );
+    }
+
+    #[test]
+    fn losses_leave_the_guess_distribution_alone() {
+        let mut stats = PlayerStats::default();
+        stats.record(false, 6);
+        assert_eq!(stats.guess_distribution, vec![0; MAX_GUESSES]);
+        assert_eq!(stats.win_percentage, 0);
+    }
+
+    fn daily_round(solution_word: &str, guesses: usize) -> RoundRecord {
+        RoundRecord {
+            game_id: "ABCD".to_string(),
+            solution_word: solution_word.to_string(),
+            guesses: vec![solution_word.to_string(); guesses],
+            players: vec!["p1".to_string()],
+            winners: vec!["p1".to_string()],
+            mode: GameMode::Daily,
+            game_status: GameStatus::Won,
+            finished_at: 1,
+        }
+    }
+
+    #[test]
+    fn replayed_daily_puzzles_are_not_recorded() {
+        let mut stats = PlayerStats::default();
+        assert!(stats.record_round("p1", &daily_round("crane", 2)));
+        assert!(!stats.record_round("p1", &daily_round("crane", 1)));
+        assert!(stats.record_round("p1", &daily_round("slate", 4)));
+        assert_eq!((stats.games_played, stats.games_won), (2, 2));
+        assert_eq!(stats.winning_guesses(), 6);
+    }
+}
@@ -0,0 +1,173 @@
+use super::{AccountRecord, GameRecord, PlayerStats, RoundRecord, Storage, StorageError};
+use crate::game::{GameId, GameMode, UserId};
+use async_trait::async_trait;
+use std::{collections::HashMap, sync::Mutex};
+
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    games: Mutex<HashMap<GameId, GameRecord>>,
+    rounds: Mutex<Vec<RoundRecord>>,
+    /// Keyed by mode as well, `None` holds the stats across every mode.
+    player_stats: Mutex<HashMap<(UserId, Option<GameMode>), PlayerStats>>,
+    ratings: Mutex<HashMap<UserId, f64>>,
+    accounts: Mutex<HashMap<String, AccountRecord>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn save_game(&self, game: GameRecord) -> Result<(), StorageError> {
+        self.games.lock().unwrap().insert(game.game_id.clone(), game);
+        Ok(())
+    }
+
+    async fn load_game(&self, game_id: &str) -> Result<Option<GameRecord>, StorageError> {
+        Ok(self.games.lock().unwrap().get(game_id).cloned())
+    }
+
+    async fn delete_game(&self, game_id: &str) -> Result<(), StorageError> {
+        self.games.lock().unwrap().remove(game_id);
+        Ok(())
+    }
+
+    async fn append_round(&self, round: RoundRecord) -> Result<(), StorageError> {
+        self.rounds.lock().unwrap().push(round);
+        Ok(())
+    }
+
+    async fn load_rounds(&self, since: u64) -> Result<Vec<RoundRecord>, StorageError> {
+        let rounds = self.rounds.lock().unwrap();
+        Ok(rounds
+            .iter()
+            .filter(|round| round.finished_at >= since)
+            .cloned()
+            .collect())
+    }
+
+    async fn record_player_stats(&self, user_id: &str, round: &RoundRecord) -> Result<(), StorageError> {
+        let mut player_stats = self.player_stats.lock().unwrap();
+        let overall = player_stats.entry((user_id.to_string(), None)).or_default();
+        if overall.record_round(user_id, round) {
+            player_stats
+                .entry((user_id.to_string(), Some(round.mode)))
+                .or_default()
+                .record_round(user_id, round);
+        }
+        Ok(())
+    }
+
+    async fn load_player_stats(&self, user_id: &str) -> Result<Option<PlayerStats>, StorageError> {
+        Ok(self
+            .player_stats
+            .lock()
+            .unwrap()
+            .get(&(user_id.to_string(), None))
+            .cloned())
+    }
+
+    async fn load_all_player_stats(
+        &self,
+        mode: Option<GameMode>,
+    ) -> Result<HashMap<UserId, PlayerStats>, StorageError> {
+        let player_stats = self.player_stats.lock().unwrap();
+        Ok(player_stats
+            .iter()
+            .filter(|((_, stats_mode), _)| *stats_mode == mode)
+            .map(|((user_id, _), stats)| (user_id.clone(), stats.clone()))
+            .collect())
+    }
+
+    async fn load_rating(&self, user_id: &str) -> Result<Option<f64>, StorageError> {
+        Ok(self.ratings.lock().unwrap().get(user_id).copied())
+    }
+
+    async fn save_rating(&self, user_id: &str, rating: f64) -> Result<(), StorageError> {
+        self.ratings.lock().unwrap().insert(user_id.to_string(), rating);
+        Ok(())
+    }
+
+    async fn load_ratings(&self) -> Result<HashMap<UserId, f64>, StorageError> {
+        Ok(self.ratings.lock().unwrap().clone())
+    }
+
+    async fn create_account(&self, account: AccountRecord) -> Result<bool, StorageError> {
+        let mut accounts = self.accounts.lock().unwrap();
+        let username = account.username.to_lowercase();
+        if accounts.contains_key(&username) {
+            return Ok(false);
+        }
+        accounts.insert(username, account);
+        Ok(true)
+    }
+
+    async fn load_account(&self, username: &str) -> Result<Option<AccountRecord>, StorageError> {
+        Ok(self.accounts.lock().unwrap().get(&username.to_lowercase()).cloned())
+    }
+
+    async fn load_usernames(&self, user_ids: Vec<UserId>) -> Result<HashMap<UserId, String>, StorageError> {
+        let accounts = self.accounts.lock().unwrap();
+        Ok(accounts
+            .values()
+            .filter(|account| user_ids.contains(&account.user_id))
+            .map(|account| (account.user_id.clone(), account.username.clone()))
+            .collect())
+    }
+}
+
+/// Assertion helpers for tests that run a coordinator against an in-memory store.
+#[cfg(test)]
+impl MemoryStorage {
+    pub fn assert_game_saved(&self, game_id: &str) -> GameRecord {
+        self.games
+            .lock()
+            .unwrap()
+            .get(game_id)
+            .cloned()
+            .unwrap_or_else(|| panic!("No game record stored for {game_id}"))
+    }
+
+    pub fn assert_game_deleted(&self, game_id: &str) {
+        assert!(
+            !self.games.lock().unwrap().contains_key(game_id),
+            "Game record for {game_id} should have been deleted"
+        );
+    }
+
+    pub fn assert_rounds(&self, game_id: &str, expected: usize) -> Vec<RoundRecord> {
+        let rounds: Vec<RoundRecord> = self
+            .rounds
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|round| round.game_id == game_id)
+            .cloned()
+            .collect();
+        assert_eq!(
+            rounds.len(),
+            expected,
+            "Unexpected number of rounds stored for {game_id}"
+        );
+        rounds
+    }
+
+    pub fn assert_player_stats(&self, user_id: &str, expected: PlayerStats) {
+        let stats = self
+            .player_stats
+            .lock()
+            .unwrap()
+            .get(&(user_id.to_string(), None))
+            .cloned()
+            .unwrap_or_default();
+        assert_eq!(stats, expected, "Unexpected stats stored for {user_id}");
+    }
+
+    pub fn assert_rating(&self, user_id: &str, expected: f64) {
+        let rating = self.ratings.lock().unwrap().get(user_id).copied();
+        assert_eq!(rating, Some(expected), "Unexpected rating stored for {user_id}");
+    }
+}
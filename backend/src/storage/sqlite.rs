@@ -0,0 +1,614 @@
+use super::{AccountRecord, GameRecord, PlayerStats, RoundRecord, Storage, StorageError, now_secs};
+use crate::game::{GameMode, UserId};
+use async_trait::async_trait;
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::task;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS games (
+        game_id TEXT PRIMARY KEY,
+        solution_word TEXT NOT NULL,
+        board_state TEXT NOT NULL,
+        password_hash TEXT,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS rounds (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        game_id TEXT NOT NULL,
+        solution_word TEXT NOT NULL,
+        guesses TEXT NOT NULL,
+        players TEXT NOT NULL,
+        winners TEXT NOT NULL,
+        mode TEXT NOT NULL,
+        game_status TEXT NOT NULL,
+        finished_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS player_stats (
+        user_id TEXT NOT NULL,
+        mode TEXT NOT NULL,
+        games_played INTEGER NOT NULL DEFAULT 0,
+        games_won INTEGER NOT NULL DEFAULT 0,
+        current_streak INTEGER NOT NULL DEFAULT 0,
+        max_streak INTEGER NOT NULL DEFAULT 0,
+        guess_distribution TEXT NOT NULL DEFAULT '[]',
+        last_daily_word TEXT,
+        PRIMARY KEY (user_id, mode)
+    );
+    CREATE TABLE IF NOT EXISTS ratings (
+        user_id TEXT PRIMARY KEY,
+        rating REAL NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS accounts (
+        user_id TEXT PRIMARY KEY,
+        username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        password_hash TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+";
+
+/// `games_played, games_won, current_streak, max_streak, guess_distribution, last_daily_word`
+type PlayerStatsColumns = (u32, u32, u32, u32, String, Option<String>);
+
+#[derive(Debug, Clone)]
+pub struct SqliteStorage {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &str) -> Result<Self, StorageError> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(SqliteStorage {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    fn select_player_stats(
+        conn: &Connection,
+        user_id: &str,
+        mode: Option<GameMode>,
+    ) -> Result<Option<PlayerStats>, StorageError> {
+        let row = conn
+            .query_row(
+                "SELECT games_played, games_won, current_streak, max_streak, guess_distribution, last_daily_word
+                 FROM player_stats WHERE user_id = ?1 AND mode = ?2",
+                params![user_id, serde_json::to_string(&mode)?],
+                |row| Self::player_stats_columns(row, 0),
+            )
+            .optional()?;
+        row.map(Self::player_stats_from_columns).transpose()
+    }
+
+    fn upsert_player_stats(
+        conn: &Connection,
+        user_id: &str,
+        mode: Option<GameMode>,
+        stats: &PlayerStats,
+    ) -> Result<(), StorageError> {
+        conn.execute(
+            "INSERT INTO player_stats
+                (user_id, mode, games_played, games_won, current_streak, max_streak, guess_distribution, last_daily_word)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(user_id, mode) DO UPDATE SET
+                games_played = excluded.games_played,
+                games_won = excluded.games_won,
+                current_streak = excluded.current_streak,
+                max_streak = excluded.max_streak,
+                guess_distribution = excluded.guess_distribution,
+                last_daily_word = excluded.last_daily_word",
+            params![
+                user_id,
+                serde_json::to_string(&mode)?,
+                stats.games_played,
+                stats.games_won,
+                stats.current_streak,
+                stats.max_streak,
+                serde_json::to_string(&stats.guess_distribution)?,
+                stats.last_daily_word
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Reads the stats columns in the order they are selected, starting at `first`.
+    fn player_stats_columns(row: &Row<'_>, first: usize) -> rusqlite::Result<PlayerStatsColumns> {
+        Ok((
+            row.get(first)?,
+            row.get(first + 1)?,
+            row.get(first + 2)?,
+            row.get(first + 3)?,
+            row.get(first + 4)?,
+            row.get(first + 5)?,
+        ))
+    }
+
+    fn player_stats_from_columns(columns: PlayerStatsColumns) -> Result<PlayerStats, StorageError> {
+        let (games_played, games_won, current_streak, max_streak, guess_distribution, last_daily_word) = columns;
+        let mut guess_distribution: Vec<u32> = serde_json::from_str(&guess_distribution)?;
+        guess_distribution.resize(PlayerStats::default().guess_distribution.len(), 0);
+        Ok(PlayerStats {
+            games_played,
+            games_won,
+            win_percentage: (games_won * 100).checked_div(games_played).unwrap_or_default(),
+            current_streak,
+            max_streak,
+            guess_distribution,
+            last_daily_word,
+        })
+    }
+
+    async fn with_connection<T, F>(&self, f: F) -> Result<T, StorageError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, StorageError> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        task::spawn_blocking(move || f(&connection.lock().unwrap())).await?
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn save_game(&self, game: GameRecord) -> Result<(), StorageError> {
+        let board_state = serde_json::to_string(&game.board_state)?;
+        self.with_connection(move |conn| {
+            conn.execute(
+                "INSERT INTO games (game_id, solution_word, board_state, password_hash, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(game_id) DO UPDATE SET
+                    solution_word = excluded.solution_word,
+                    board_state = excluded.board_state,
+                    password_hash = excluded.password_hash,
+                    updated_at = excluded.updated_at",
+                params![
+                    game.game_id,
+                    game.solution_word,
+                    board_state,
+                    game.password_hash,
+                    game.updated_at
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn load_game(&self, game_id: &str) -> Result<Option<GameRecord>, StorageError> {
+        let game_id = game_id.to_string();
+        self.with_connection(move |conn| {
+            let row = conn
+                .query_row(
+                    "SELECT solution_word, board_state, password_hash, updated_at FROM games WHERE game_id = ?1",
+                    params![game_id],
+                    |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, Option<String>>(2)?,
+                            row.get::<_, u64>(3)?,
+                        ))
+                    },
+                )
+                .optional()?;
+            let Some((solution_word, board_state, password_hash, updated_at)) = row else {
+                return Ok(None);
+            };
+            Ok(Some(GameRecord {
+                game_id,
+                solution_word,
+                board_state: serde_json::from_str(&board_state)?,
+                password_hash,
+                updated_at,
+            }))
+        })
+        .await
+    }
+
+    async fn delete_game(&self, game_id: &str) -> Result<(), StorageError> {
+        let game_id = game_id.to_string();
+        self.with_connection(move |conn| {
+            conn.execute("DELETE FROM games WHERE game_id = ?1", params![game_id])?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn append_round(&self, round: RoundRecord) -> Result<(), StorageError> {
+        let guesses = serde_json::to_string(&round.guesses)?;
+        let players = serde_json::to_string(&round.players)?;
+        let winners = serde_json::to_string(&round.winners)?;
+        let mode = serde_json::to_string(&round.mode)?;
+        let game_status = serde_json::to_string(&round.game_status)?;
+        self.with_connection(move |conn| {
+            conn.execute(
+                "INSERT INTO rounds (game_id, solution_word, guesses, players, winners, mode, game_status, finished_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    round.game_id,
+                    round.solution_word,
+                    guesses,
+                    players,
+                    winners,
+                    mode,
+                    game_status,
+                    round.finished_at
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn load_rounds(&self, since: u64) -> Result<Vec<RoundRecord>, StorageError> {
+        self.with_connection(move |conn| {
+            let mut statement = conn.prepare(
+                "SELECT game_id, solution_word, guesses, players, winners, mode, game_status, finished_at
+                 FROM rounds WHERE finished_at >= ?1 ORDER BY finished_at, id",
+            )?;
+            let rows = statement.query_map(params![since], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, String>(6)?,
+                    row.get::<_, u64>(7)?,
+                ))
+            })?;
+            let mut rounds = Vec::new();
+            for row in rows {
+                let (game_id, solution_word, guesses, players, winners, mode, game_status, finished_at) = row?;
+                rounds.push(RoundRecord {
+                    game_id,
+                    solution_word,
+                    guesses: serde_json::from_str(&guesses)?,
+                    players: serde_json::from_str(&players)?,
+                    winners: serde_json::from_str(&winners)?,
+                    mode: serde_json::from_str(&mode)?,
+                    game_status: serde_json::from_str(&game_status)?,
+                    finished_at,
+                });
+            }
+            Ok(rounds)
+        })
+        .await
+    }
+
+    async fn record_player_stats(&self, user_id: &str, round: &RoundRecord) -> Result<(), StorageError> {
+        let user_id = user_id.to_string();
+        let round = round.clone();
+        self.with_connection(move |conn| {
+            let mut overall = Self::select_player_stats(conn, &user_id, None)?.unwrap_or_default();
+            if !overall.record_round(&user_id, &round) {
+                return Ok(());
+            }
+            let mode = Some(round.mode);
+            let mut by_mode = Self::select_player_stats(conn, &user_id, mode)?.unwrap_or_default();
+            by_mode.record_round(&user_id, &round);
+            Self::upsert_player_stats(conn, &user_id, None, &overall)?;
+            Self::upsert_player_stats(conn, &user_id, mode, &by_mode)
+        })
+        .await
+    }
+
+    async fn load_player_stats(&self, user_id: &str) -> Result<Option<PlayerStats>, StorageError> {
+        let user_id = user_id.to_string();
+        self.with_connection(move |conn| Self::select_player_stats(conn, &user_id, None))
+            .await
+    }
+
+    async fn load_all_player_stats(
+        &self,
+        mode: Option<GameMode>,
+    ) -> Result<HashMap<UserId, PlayerStats>, StorageError> {
+        self.with_connection(move |conn| {
+            let mut statement = conn.prepare(
+                "SELECT user_id, games_played, games_won, current_streak, max_streak, guess_distribution, last_daily_word
+                 FROM player_stats WHERE mode = ?1",
+            )?;
+            let rows = statement.query_map(params![serde_json::to_string(&mode)?], |row| {
+                Ok((row.get::<_, String>(0)?, Self::player_stats_columns(row, 1)?))
+            })?;
+            let mut player_stats = HashMap::new();
+            for row in rows {
+                let (user_id, columns) = row?;
+                player_stats.insert(user_id, Self::player_stats_from_columns(columns)?);
+            }
+            Ok(player_stats)
+        })
+        .await
+    }
+
+    async fn load_rating(&self, user_id: &str) -> Result<Option<f64>, StorageError> {
+        let user_id = user_id.to_string();
+        self.with_connection(move |conn| {
+            let rating = conn
+                .query_row(
+                    "SELECT rating FROM ratings WHERE user_id = ?1",
+                    params![user_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(rating)
+        })
+        .await
+    }
+
+    async fn save_rating(&self, user_id: &str, rating: f64) -> Result<(), StorageError> {
+        let user_id = user_id.to_string();
+        self.with_connection(move |conn| {
+            conn.execute(
+                "INSERT INTO ratings (user_id, rating, updated_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(user_id) DO UPDATE SET
+                    rating = excluded.rating,
+                    updated_at = excluded.updated_at",
+                params![user_id, rating, now_secs()],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn load_ratings(&self) -> Result<HashMap<UserId, f64>, StorageError> {
+        self.with_connection(move |conn| {
+            let mut statement = conn.prepare("SELECT user_id, rating FROM ratings")?;
+            let ratings = statement
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<_, _>>()?;
+            Ok(ratings)
+        })
+        .await
+    }
+
+    async fn create_account(&self, account: AccountRecord) -> Result<bool, StorageError> {
+        self.with_connection(move |conn| {
+            let inserted = conn.execute(
+                "INSERT INTO accounts (user_id, username, password_hash, created_at) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(username) DO NOTHING",
+                params![
+                    account.user_id,
+                    account.username,
+                    account.password_hash,
+                    account.created_at
+                ],
+            )?;
+            Ok(inserted == 1)
+        })
+        .await
+    }
+
+    async fn load_account(&self, username: &str) -> Result<Option<AccountRecord>, StorageError> {
+        let username = username.to_string();
+        self.with_connection(move |conn| {
+            let account = conn
+                .query_row(
+                    "SELECT user_id, username, password_hash, created_at FROM accounts WHERE username = ?1",
+                    params![username],
+                    |row| {
+                        Ok(AccountRecord {
+                            user_id: row.get(0)?,
+                            username: row.get(1)?,
+                            password_hash: row.get(2)?,
+                            created_at: row.get(3)?,
+                        })
+                    },
+                )
+                .optional()?;
+            Ok(account)
+        })
+        .await
+    }
+
+    async fn load_usernames(&self, user_ids: Vec<UserId>) -> Result<HashMap<UserId, String>, StorageError> {
+        self.with_connection(move |conn| {
+            let mut statement = conn.prepare("SELECT username FROM accounts WHERE user_id = ?1")?;
+            let mut usernames = HashMap::new();
+            for user_id in user_ids {
+                if let Some(username) = statement.query_row(params![user_id], |row| row.get(0)).optional()? {
+                    usernames.insert(user_id, username);
+                }
+            }
+            Ok(usernames)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{BoardState, GameStatus};
+
+    fn storage() -> SqliteStorage {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(SCHEMA).unwrap();
+        SqliteStorage {
+            connection: Arc::new(Mutex::new(connection)),
+        }
+    }
+
+    fn board_state() -> BoardState {
+        serde_json::from_value(serde_json::json!({
+            "guesses": [],
+            "currentTurn": "host",
+            "gameStatus": "inProgress",
+            "keyboardStatus": {},
+            "players": [{ "handle": "host", "displayName": "Player", "avatarColor": null }],
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn games_round_trip_and_update_in_place() {
+        let storage = storage();
+        assert!(storage.load_game("ABCD").await.unwrap().is_none());
+
+        let mut game = GameRecord {
+            game_id: "ABCD".to_string(),
+            solution_word: "crane".to_string(),
+            board_state: board_state(),
+            password_hash: Some("hash".to_string()),
+            updated_at: 1,
+        };
+        storage.save_game(game.clone()).await.unwrap();
+        game.solution_word = "slate".to_string();
+        game.updated_at = 2;
+        storage.save_game(game).await.unwrap();
+
+        let loaded = storage.load_game("ABCD").await.unwrap().unwrap();
+        assert_eq!(loaded.game_id, "ABCD");
+        assert_eq!(loaded.solution_word, "slate");
+        assert_eq!(loaded.password_hash.as_deref(), Some("hash"));
+        assert_eq!(loaded.updated_at, 2);
+        assert_eq!(
+            serde_json::to_value(&loaded.board_state).unwrap(),
+            serde_json::to_value(board_state()).unwrap()
+        );
+
+        storage.delete_game("ABCD").await.unwrap();
+        assert!(storage.load_game("ABCD").await.unwrap().is_none());
+    }
+
+    fn round(mode: GameMode, solution_word: &str, guesses: usize, winners: &[&str]) -> RoundRecord {
+        RoundRecord {
+            game_id: "ABCD".to_string(),
+            solution_word: solution_word.to_string(),
+            guesses: vec![solution_word.to_string(); guesses],
+            players: vec!["p1".to_string(), "p2".to_string()],
+            winners: winners.iter().map(|winner| winner.to_string()).collect(),
+            mode,
+            game_status: if winners.is_empty() {
+                GameStatus::Lost
+            } else {
+                GameStatus::Won
+            },
+            finished_at: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn rounds_are_appended_and_loaded_since() {
+        let storage = storage();
+        let mut first = round(GameMode::Competitive, "crane", 2, &["p1"]);
+        first.guesses = vec!["slate".to_string(), "crane".to_string()];
+        storage.append_round(first).await.unwrap();
+        let mut second = round(GameMode::Coop, "slate", 6, &[]);
+        second.finished_at = 5;
+        storage.append_round(second).await.unwrap();
+
+        let rounds = storage.load_rounds(0).await.unwrap();
+        assert_eq!(rounds.len(), 2);
+        assert_eq!(rounds[0].guesses, vec!["slate", "crane"]);
+        assert_eq!(rounds[0].players, vec!["p1", "p2"]);
+        assert_eq!(rounds[0].winners, vec!["p1"]);
+        assert_eq!(rounds[0].mode, GameMode::Competitive);
+        assert!(matches!(rounds[0].game_status, GameStatus::Won));
+
+        let rounds = storage.load_rounds(2).await.unwrap();
+        assert_eq!(rounds.len(), 1);
+        assert_eq!(rounds[0].solution_word, "slate");
+    }
+
+    #[tokio::test]
+    async fn player_stats_add_up_overall_and_per_mode() {
+        let storage = storage();
+        assert!(storage.load_player_stats("p1").await.unwrap().is_none());
+        storage
+            .record_player_stats("p1", &round(GameMode::Coop, "crane", 2, &["p1", "p2"]))
+            .await
+            .unwrap();
+        storage
+            .record_player_stats("p1", &round(GameMode::Competitive, "slate", 2, &["p1"]))
+            .await
+            .unwrap();
+        storage
+            .record_player_stats("p1", &round(GameMode::Competitive, "pious", 6, &["p2"]))
+            .await
+            .unwrap();
+        storage
+            .record_player_stats("p2", &round(GameMode::Competitive, "pious", 6, &["p2"]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.load_player_stats("p1").await.unwrap().unwrap(),
+            PlayerStats {
+                games_played: 3,
+                games_won: 2,
+                win_percentage: 66,
+                current_streak: 0,
+                max_streak: 2,
+                guess_distribution: vec![0, 2, 0, 0, 0, 0],
+                last_daily_word: None,
+            }
+        );
+        let competitive = storage
+            .load_all_player_stats(Some(GameMode::Competitive))
+            .await
+            .unwrap();
+        assert_eq!(competitive.len(), 2);
+        assert_eq!((competitive["p1"].games_played, competitive["p1"].games_won), (2, 1));
+        assert_eq!((competitive["p2"].games_played, competitive["p2"].games_won), (1, 1));
+        assert_eq!(storage.load_all_player_stats(None).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn daily_puzzles_only_count_once() {
+        let storage = storage();
+        let daily = round(GameMode::Daily, "crane", 3, &["p1", "p2"]);
+        storage.record_player_stats("p1", &daily).await.unwrap();
+        storage.record_player_stats("p1", &daily).await.unwrap();
+
+        let stats = storage.load_player_stats("p1").await.unwrap().unwrap();
+        assert_eq!((stats.games_played, stats.games_won), (1, 1));
+        assert_eq!(stats.last_daily_word.as_deref(), Some("crane"));
+        let daily_stats = storage.load_all_player_stats(Some(GameMode::Daily)).await.unwrap();
+        assert_eq!(daily_stats["p1"].games_played, 1);
+
+        storage
+            .record_player_stats("p1", &round(GameMode::Daily, "slate", 4, &["p1", "p2"]))
+            .await
+            .unwrap();
+        let stats = storage.load_player_stats("p1").await.unwrap().unwrap();
+        assert_eq!(stats.games_played, 2);
+    }
+
+    #[tokio::test]
+    async fn ratings_round_trip() {
+        let storage = storage();
+        assert_eq!(storage.load_rating("u1").await.unwrap(), None);
+        storage.save_rating("u1", 1216.0).await.unwrap();
+        storage.save_rating("u1", 1230.5).await.unwrap();
+        assert_eq!(storage.load_rating("u1").await.unwrap(), Some(1230.5));
+    }
+
+    #[tokio::test]
+    async fn accounts_round_trip_with_unique_usernames() {
+        let storage = storage();
+        let account = AccountRecord {
+            user_id: "u1".to_string(),
+            username: "Player".to_string(),
+            password_hash: "hash".to_string(),
+            created_at: 1,
+        };
+        assert!(storage.create_account(account.clone()).await.unwrap());
+
+        let taken = AccountRecord {
+            user_id: "u2".to_string(),
+            username: "player".to_string(),
+            ..account
+        };
+        assert!(!storage.create_account(taken).await.unwrap());
+
+        let loaded = storage.load_account("PLAYER").await.unwrap().unwrap();
+        assert_eq!((loaded.user_id.as_str(), loaded.username.as_str()), ("u1", "Player"));
+        assert!(storage.load_account("stranger").await.unwrap().is_none());
+    }
+}
@@ -1,45 +1,257 @@
-use crate::game::{GameCommand, GameId, PlayerId, ServerMessage};
+use crate::dict;
+use crate::game::{
+    GameCommand, GameError, GameId, GameMode, GameSettings, PlayerHandle, PublicPlayer, ReplySender, RequestId,
+    RoomOptions, ServerMessage, UserId,
+};
+use crate::leaderboard::{LeaderboardQuery, Leaderboards};
+use crate::matchmaking;
+use crate::protocol::{self, Encoding};
+use crate::session::{self, ResumeTokens, UserTokens};
+use crate::storage::SharedStorage;
 use axum::extract::ws::{Message, WebSocket};
-use futures::{sink::SinkExt, stream::StreamExt};
+use futures::{
+    sink::SinkExt,
+    stream::{SplitSink, StreamExt},
+};
 use governor::{Quota, RateLimiter};
 use nonzero_ext::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_json;
 use tokio::{
-    sync::mpsc,
+    sync::{broadcast, mpsc},
     time::{Duration, Instant, interval_at},
 };
 use tracing::{error, info, instrument};
+use ts_rs::TS;
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug)]
 #[serde(tag = "action", rename_all = "camelCase", rename_all_fields = "camelCase")]
-enum ClientMessage {
+#[ts(optional_fields = nullable)]
+pub(crate) enum ClientMessage {
     Connect {
         game_id: Option<GameId>,
-        old_player_id: Option<PlayerId>,
+        resume_token: Option<String>,
+        user_token: Option<String>,
+        display_name: Option<String>,
+        avatar_color: Option<String>,
+        password: Option<String>,
+        protocol_version: Option<u32>,
     },
     JoinGame {
         game_id: GameId,
+        password: Option<String>,
     },
     GuessWord {
         word: String,
     },
-    CreateGame,
+    CreateGame {
+        password: Option<String>,
+        #[serde(default)]
+        #[ts(optional, as = "Option<bool>")]
+        public: bool,
+    },
     NewGame,
     DisconnectPlayer,
+    KickPlayer {
+        handle: PlayerHandle,
+    },
+    LockRoom {
+        locked: bool,
+    },
+    SetPublic {
+        public: bool,
+    },
+    UpdateSettings {
+        settings: GameSettings,
+    },
+    TransferHost {
+        handle: PlayerHandle,
+    },
+    ListRooms,
+    FindMatch {
+        mode: GameMode,
+        word_length: Option<usize>,
+    },
+    CancelMatch,
+    /// Defaults to the stats of the connected player.
+    GetStats {
+        user_id: Option<UserId>,
+    },
+    Chat {
+        text: String,
+    },
+    DraftUpdate {
+        letters: String,
+    },
+    React {
+        guess_index: usize,
+        emoji: String,
+    },
+    SuggestWord {
+        word: String,
+    },
+    /// Guesses the word the named teammate suggested this turn.
+    AcceptSuggestion {
+        from: PlayerHandle,
+    },
+    GetLeaderboard {
+        #[serde(flatten)]
+        query: LeaderboardQuery,
+    },
+    /// Asks for the full board, after a client notices a gap in delta versions.
+    GetSnapshot,
+}
+
+/// Wrong room passwords a connection may send before it can't join rooms anymore, until a join succeeds.
+const MAX_FAILED_PASSWORDS: u32 = 5;
+
+/// Any client message, optionally tagged with an id that the `Ack` or `Error` answering it will carry.
+#[derive(Serialize, Deserialize, TS, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields = nullable)]
+pub(crate) struct ClientRequest {
+    request_id: Option<RequestId>,
+    #[serde(flatten)]
+    message: ClientMessage,
+}
+
+/// Every `action` a `ClientMessage` can have, to tell an unknown action apart from a malformed known one.
+const CLIENT_ACTIONS: &[&str] = &[
+    "connect",
+    "joinGame",
+    "guessWord",
+    "createGame",
+    "newGame",
+    "disconnectPlayer",
+    "kickPlayer",
+    "lockRoom",
+    "setPublic",
+    "updateSettings",
+    "transferHost",
+    "listRooms",
+    "findMatch",
+    "cancelMatch",
+    "getStats",
+    "chat",
+    "draftUpdate",
+    "react",
+    "suggestWord",
+    "acceptSuggestion",
+    "getLeaderboard",
+    "getSnapshot",
+];
+
+fn not_connected() -> GameError {
+    GameError::NotConnected {
+        message: "Send connect first".to_string(),
+    }
+}
+
+/// Reads a client message decoded from either encoding, on failure the error still carries the `requestId` if one could be read.
+fn parse_request(value: Option<serde_json::Value>) -> Result<ClientRequest, (Option<RequestId>, GameError)> {
+    let Some(value) = value else {
+        let error = GameError::InvalidMessage {
+            message: "Message is not valid JSON or MessagePack".to_string(),
+        };
+        return Err((None, error));
+    };
+    let request_id = value.get("requestId").and_then(|id| id.as_str()).map(str::to_string);
+    let action = value
+        .get("action")
+        .and_then(|action| action.as_str())
+        .map(str::to_string);
+
+    serde_json::from_value::<ClientRequest>(value).map_err(|err| {
+        let error = match action {
+            None => GameError::InvalidMessage {
+                message: "Message has no action".to_string(),
+            },
+            Some(action) if !CLIENT_ACTIONS.contains(&action.as_str()) => GameError::UnknownAction {
+                message: format!("Unknown action {action}"),
+                action,
+            },
+            Some(action) => GameError::InvalidMessage {
+                message: format!("Invalid {action} message: {err}"),
+            },
+        };
+        (request_id, error)
+    })
 }
 
-#[instrument(skip(socket, tx))]
-pub async fn handle_socket(socket: WebSocket, tx: mpsc::Sender<GameCommand>) {
+async fn send_server_message(tw: &mut SplitSink<WebSocket, Message>, encoding: Encoding, message: &ServerMessage) {
+    let Some(message) = encoding.encode(message) else {
+        error!("Failed to serialize message");
+        return;
+    };
+    if let Err(err) = tw.send(message).await {
+        error!("Unable to send message to client {err:?}");
+    }
+}
+
+async fn send_error(
+    tw: &mut SplitSink<WebSocket, Message>,
+    encoding: Encoding,
+    error: GameError,
+    request_id: Option<RequestId>,
+) {
+    send_server_message(tw, encoding, &ServerMessage::Error { error, request_id }).await;
+}
+
+async fn send_ack(tw: &mut SplitSink<WebSocket, Message>, encoding: Encoding, request_id: Option<RequestId>) {
+    if let Some(request_id) = request_id {
+        send_server_message(tw, encoding, &ServerMessage::Ack { request_id }).await;
+    }
+}
+
+/// Everything a socket reads or signs on its own, without going through the coordinator.
+#[derive(Debug, Clone)]
+pub struct SocketContext {
+    pub resume_tokens: ResumeTokens,
+    pub user_tokens: UserTokens,
+    pub storage: SharedStorage,
+    pub leaderboards: Leaderboards,
+}
+
+#[instrument(skip(socket, tx, announcements, context))]
+pub async fn handle_socket(
+    socket: WebSocket,
+    tx: mpsc::Sender<GameCommand>,
+    mut announcements: broadcast::Receiver<ServerMessage>,
+    context: SocketContext,
+    account: Option<UserId>,
+) {
+    let SocketContext {
+        resume_tokens,
+        user_tokens,
+        storage,
+        leaderboards,
+    } = context;
     let (player_tx, mut player_rx) = mpsc::channel::<ServerMessage>(32);
     let mut session_game_id: Option<String> = None;
+    // The room a join was sent for, the session only moves there once the game accepts it.
+    let mut pending_game_id: Option<String> = None;
     let mut session_player_id: Option<String> = None;
+    let mut session_user_id: Option<UserId> = None;
+    let mut session_profile: Option<PublicPlayer> = None;
+    let mut resume_game_id: Option<String> = None;
+    let mut failed_passwords = 0;
+    let mut searching_match = false;
+    let mut session_protocol_version = protocol::PROTOCOL_VERSION;
+    let too_many_passwords = GameError::JoinError {
+        message: "Too many wrong room passwords".to_string(),
+    };
 
+    let encoding = Encoding::negotiated(socket.protocol());
     let (mut tw, mut rw) = socket.split();
 
     let quota = Quota::per_second(nonzero!(1u32)).allow_burst(nonzero!(6u32));
     let limit = RateLimiter::direct(quota);
+    // Chat gets its own budget so talking never eats into the guesses.
+    let chat_quota = Quota::per_minute(nonzero!(20u32)).allow_burst(nonzero!(5u32));
+    let chat_limit = RateLimiter::direct(chat_quota);
+    let draft_quota = Quota::per_second(nonzero!(10u32));
+    let draft_limit = RateLimiter::direct(draft_quota);
 
     let interval = Duration::from_secs(30);
     let start = Instant::now() + interval;
@@ -61,89 +273,294 @@ pub async fn handle_socket(socket: WebSocket, tx: mpsc::Sender<GameCommand>) {
 
             }
             Some(msg) = player_rx.recv() => {
-                if let ServerMessage::Created {game_id, ..} = &msg {
-                    session_game_id = Some(game_id.clone());
-                }
-                let Ok(message) = serde_json::to_string(&msg) else {
-                    error!("Failed to serialize message");
+                if let ServerMessage::BoardDelta { .. } = &msg
+                    && session_protocol_version < protocol::BOARD_DELTA_VERSION
+                {
+                    // Older clients only understand full updates, so they are sent a snapshot of the board instead.
+                    if let (Some(player_id), Some(game_id)) = (session_player_id.clone(), session_game_id.clone()) {
+                        let reply_sender = ReplySender::new(player_tx.clone(), None);
+                        let _ = tx.send(GameCommand::Snapshot { game_id, player_id, reply_sender }).await;
+                    }
                     continue;
-                };
-                if let Err(err) = tw.send(Message::Text(message.into())).await {
-                    error!("Unable to send message to client {err:?}");
+                }
+                match &msg {
+                    ServerMessage::Created { game_id, .. } | ServerMessage::Joined { game_id, .. } => {
+                        session_game_id = Some(game_id.clone());
+                        if pending_game_id.as_ref() == Some(game_id) {
+                            pending_game_id = None;
+                            failed_passwords = 0;
+                        }
+                        searching_match = false;
+                    }
+                    ServerMessage::MatchCancelled | ServerMessage::MatchTimedOut => searching_match = false,
+                    ServerMessage::RoomClosed { .. } | ServerMessage::Kicked { .. } => session_game_id = None,
+                    ServerMessage::Error { error: GameError::JoinError { .. }, .. } => pending_game_id = None,
+                    ServerMessage::Error { error: GameError::PasswordError { .. }, .. } => {
+                        pending_game_id = None;
+                        failed_passwords += 1;
+                    }
+                    _ => {}
+                }
+                send_server_message(&mut tw, encoding, &msg).await;
+
+                if let ServerMessage::Created { game_id, .. } | ServerMessage::Joined { game_id, .. } = &msg
+                    && let (Some(player_id), Some(profile)) = (&session_player_id, &session_profile)
+                    && resume_game_id.as_ref() != Some(game_id)
+                {
+                    resume_game_id = Some(game_id.clone());
+                    let resume_token = resume_tokens.issue(player_id, profile.handle(), game_id);
+                    send_server_message(&mut tw, encoding, &ServerMessage::ResumeToken { resume_token }).await;
                 }
             }
+            Ok(msg) = announcements.recv() => {
+                send_server_message(&mut tw, encoding, &msg).await;
+            }
 
-            Some(Ok(msg)) = rw.next() => {
+            msg = rw.next() => {
+                let Some(Ok(msg)) = msg else {
+                    info!("Socket closed by client");
+                    break;
+                };
                 missed_pings = 0;
                 match msg {
-                    Message::Text(text) => {
-                        if limit.check().is_err() {
-                            let error_message = serde_json::json!({
-                                "status": "error",
-                                "error": {
-                                    "type": "rateLimitError",
-                                    "message": "Rate limit reached"
-                                }
-                            }).to_string();
-
-                            if let Err(_) = tw.send(Message::Text(error_message.into())).await {
-                                error!("Error sending rate limite messgae");
-                            }
+                    // The frontend pokes the socket with a bare ping when its tab comes back into view.
+                    Message::Text(text) if text.as_str() == "ping" => continue,
+                    Message::Text(_) | Message::Binary(_) => {
+                        let request = parse_request(protocol::decode(&msg));
+                        let (limiter, limit_message) = match &request {
+                            Ok(ClientRequest { message: ClientMessage::Chat { .. }, .. }) => (&chat_limit, Some("Slow down, you are sending messages too fast")),
+                            // Drafts are superseded by the next keystroke, extra ones are simply dropped.
+                            Ok(ClientRequest { message: ClientMessage::DraftUpdate { .. }, .. }) => (&draft_limit, None),
+                            _ => (&limit, Some("Rate limit reached")),
+                        };
+                        if limiter.check().is_err() {
+                            let Some(limit_message) = limit_message else { continue };
+                            let request_id = match &request {
+                                Ok(ClientRequest { request_id, .. }) | Err((request_id, _)) => request_id.clone(),
+                            };
+                            let error = GameError::RateLimitError { message: limit_message.to_string() };
+                            send_error(&mut tw, encoding, error, request_id).await;
                             continue;
                         }
-                        let Ok(request) = serde_json::from_str::<ClientMessage>(&text.to_string()) else {continue;};
+                        let (request_id, request) = match request {
+                            Ok(ClientRequest { request_id, message }) => (request_id, message),
+                            Err((request_id, error)) => {
+                                send_error(&mut tw, encoding, error, request_id).await;
+                                continue;
+                            }
+                        };
+                        let reply_sender = ReplySender::new(player_tx.clone(), request_id.clone());
 
-                        if let ClientMessage::Connect {game_id, old_player_id} = &request {
+                        if let ClientMessage::Connect {game_id, resume_token, user_token, display_name, avatar_color, password, protocol_version} = &request {
+                            let protocol_version = match protocol::negotiate(*protocol_version) {
+                                Ok(version) => version,
+                                Err(error) => {
+                                    send_error(&mut tw, encoding, error, request_id.clone()).await;
+                                    break;
+                                }
+                            };
                             let new_player_id = Uuid::new_v4().to_string();
+                            let user_id = account
+                                .clone()
+                                .or_else(|| user_token.as_ref().and_then(|token| user_tokens.verify(token)))
+                                .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+                            let resumed_seat = match (game_id, resume_token) {
+                                (Some(gid), Some(token)) => resume_tokens.verify(token, gid).map(Some),
+                                _ => Ok(None),
+                            };
+                            let game_id = if resumed_seat.is_ok() { game_id.clone() } else { None };
+                            let handle = match &resumed_seat {
+                                Ok(Some(seat)) => seat.handle.clone(),
+                                _ => session::random_handle(),
+                            };
+                            let profile = match PublicPlayer::new(handle, display_name.clone(), avatar_color.clone()) {
+                                Ok(profile) => profile,
+                                Err(error) => {
+                                    send_error(&mut tw, encoding, error, request_id.clone()).await;
+                                    continue;
+                                }
+                            };
 
                             session_player_id = Some(new_player_id.clone());
-                            session_game_id = game_id.clone();
+                            session_user_id = Some(user_id.clone());
+                            session_protocol_version = protocol_version;
+                            session_game_id = None;
+                            pending_game_id = game_id.clone();
 
                             info!("player connected {}", &new_player_id);
-                            let welcome_message = serde_json::to_string(&ServerMessage::Welcome {
+                            let welcome_message = ServerMessage::Welcome {
                                 player_id: new_player_id.clone(),
+                                handle: profile.handle().to_string(),
                                 message: "Welcome new player".to_string(),
-                            });
-                            if let Ok(message) = welcome_message {
-                                if tw.send(Message::Text(message.into())).await.is_err() {
-                                    error!("Unable to send welcome message");
+                                user_token: user_tokens.issue(&user_id),
+                                protocol_version,
+                                features: protocol::features(protocol_version),
+                            };
+                            send_server_message(&mut tw, encoding, &welcome_message).await;
+                            session_profile = Some(profile.clone());
+
+                            let old_player_id = match resumed_seat {
+                                Ok(seat) => seat.map(|seat| seat.player_id),
+                                Err(error) => {
+                                    send_error(&mut tw, encoding, error, request_id.clone()).await;
+                                    continue;
                                 }
-                            }
+                            };
 
-                            let Some(id) = game_id else {continue};
-                            if let Err(err) = tx.send(GameCommand::Join { game_id: id.clone(), player_id: new_player_id, old_player_id: old_player_id.clone(), reply_sender: player_tx.clone()}).await {
+                            let Some(id) = game_id else {
+                                send_ack(&mut tw, encoding, request_id).await;
+                                continue;
+                            };
+                            if old_player_id.is_none() && failed_passwords >= MAX_FAILED_PASSWORDS {
+                                pending_game_id = None;
+                                send_error(&mut tw, encoding, too_many_passwords.clone(), request_id.clone()).await;
+                                continue;
+                            }
+                            let join = GameCommand::Join { game_id: id, player_id: new_player_id, user_id, profile, old_player_id, password: password.clone(), reply_sender};
+                            if let Err(err) = tx.send(join).await {
                                 error!("Unable to send message to game coordinator {err}");
                                 break;
                             }
                             continue;
                         }
 
-                        let command = match (request, session_player_id.clone(), session_game_id.clone()) {
-                            (ClientMessage::Connect {..}, _, _) => unreachable!(),
-                            (ClientMessage::CreateGame, Some(pid), _) => {
-                                GameCommand::Create { player_id: pid, reply_sender: player_tx.clone()}
+                        if let ClientMessage::GetStats { user_id } = &request {
+                            let Some(user_id) = user_id.clone().or_else(|| session_user_id.clone()) else {
+                                send_error(&mut tw, encoding, not_connected(), request_id).await;
+                                continue;
+                            };
+                            match storage.load_player_stats(&user_id).await {
+                                Ok(stats) => {
+                                    let stats = stats.unwrap_or_default();
+                                    send_server_message(&mut tw, encoding, &ServerMessage::Stats { user_id, stats }).await;
+                                    send_ack(&mut tw, encoding, request_id).await;
+                                }
+                                Err(err) => error!("Failed to load player stats: {err}"),
+                            }
+                            continue;
+                        }
+
+                        if let ClientMessage::GetLeaderboard { query } = &request {
+                            match leaderboards.get(*query).await {
+                                Ok(entries) => {
+                                    send_server_message(&mut tw, encoding, &ServerMessage::Leaderboard { query: *query, entries }).await;
+                                    send_ack(&mut tw, encoding, request_id).await;
+                                }
+                                Err(err) => error!("Failed to load leaderboard: {err}"),
+                            }
+                            continue;
+                        }
+
+                        let user_id = session_user_id.clone().unwrap_or_default();
+                        let command = match (request, session_player_id.clone(), session_profile.clone(), session_game_id.clone()) {
+                            (ClientMessage::Connect {..} | ClientMessage::GetStats {..} | ClientMessage::GetLeaderboard {..}, _, _, _) => unreachable!(),
+                            (ClientMessage::CreateGame { password, public }, Some(pid), Some(profile), _) => {
+                                let password_hash = match session::hash_room_password(password).await {
+                                    Ok(password_hash) => password_hash,
+                                    Err(error) => {
+                                        send_error(&mut tw, encoding, error, request_id.clone()).await;
+                                        continue;
+                                    }
+                                };
+                                let options = RoomOptions { password_hash, public, ..RoomOptions::default() };
+                                GameCommand::Create { player_id: pid, user_id, profile, options, reply_sender}
                             },
-                            (ClientMessage::JoinGame { game_id }, Some(pid), _) => {
-                                session_game_id = Some(game_id.clone());
-                                GameCommand::Join { game_id: game_id, player_id: pid, old_player_id: None, reply_sender: player_tx.clone()}
+                            (ClientMessage::JoinGame { game_id, password }, Some(pid), Some(profile), _) => {
+                                if failed_passwords >= MAX_FAILED_PASSWORDS {
+                                    send_error(&mut tw, encoding, too_many_passwords.clone(), request_id.clone()).await;
+                                    continue;
+                                }
+                                // The coordinator takes the player out of their current room before joining the next one.
+                                session_game_id = None;
+                                pending_game_id = Some(game_id.clone());
+                                GameCommand::Join { game_id, player_id: pid, user_id, profile, old_player_id: None, password, reply_sender}
                             },
-                            (ClientMessage::NewGame, _, Some(gid)) => {
-                                GameCommand::New { game_id: gid, reply_sender: player_tx.clone()}
+                            (ClientMessage::NewGame, Some(pid), _, Some(gid)) => {
+                                GameCommand::New { game_id: gid, player_id: pid, reply_sender}
                             },
-                            (ClientMessage::GuessWord { word }, Some(pid), Some(gid)) => {
+                            (ClientMessage::GuessWord { word }, Some(pid), _, Some(gid)) => {
 
-                                GameCommand::Guess { game_id: gid, player_id: pid, word: word.clone(), reply_sender: player_tx.clone()}
+                                GameCommand::Guess { game_id: gid, player_id: pid, word: word.clone(), reply_sender}
                             },
-                            (ClientMessage::DisconnectPlayer, Some(pid), Some(gid)) => {
+                            (ClientMessage::DisconnectPlayer, Some(pid), _, Some(gid)) => {
                                 GameCommand::Disconnect { game_id: gid, player_id: pid, }
                             },
-                            _ => {continue}
+                            (ClientMessage::Chat { text }, Some(pid), _, Some(gid)) => {
+                                GameCommand::Chat { game_id: gid, player_id: pid, text, reply_sender}
+                            },
+                            (ClientMessage::DraftUpdate { letters }, Some(pid), _, Some(gid)) => {
+                                GameCommand::Draft { game_id: gid, player_id: pid, letters }
+                            },
+                            (ClientMessage::SuggestWord { word }, Some(pid), _, Some(gid)) => {
+                                GameCommand::Suggest { game_id: gid, player_id: pid, word, reply_sender}
+                            }
+                            (ClientMessage::AcceptSuggestion { from }, Some(pid), _, Some(gid)) => {
+                                GameCommand::AcceptSuggestion { game_id: gid, player_id: pid, from, reply_sender}
+                            }
+                            (ClientMessage::React { guess_index, emoji }, Some(pid), _, Some(gid)) => {
+                                GameCommand::React { game_id: gid, player_id: pid, guess_index, emoji, reply_sender}
+                            },
+                            (ClientMessage::KickPlayer { handle }, Some(pid), _, Some(gid)) => {
+                                GameCommand::Kick { game_id: gid, player_id: pid, target: handle, reply_sender}
+                            },
+                            (ClientMessage::LockRoom { locked }, Some(pid), _, Some(gid)) => {
+                                GameCommand::Lock { game_id: gid, player_id: pid, locked, reply_sender}
+                            },
+                            (ClientMessage::UpdateSettings { settings }, Some(pid), _, Some(gid)) => {
+                                GameCommand::UpdateSettings { game_id: gid, player_id: pid, settings, reply_sender}
+                            },
+                            (ClientMessage::SetPublic { public }, Some(pid), _, Some(gid)) => {
+                                GameCommand::SetPublic { game_id: gid, player_id: pid, public, reply_sender}
+                            },
+                            (ClientMessage::TransferHost { handle }, Some(pid), _, Some(gid)) => {
+                                GameCommand::TransferHost { game_id: gid, player_id: pid, target: handle, reply_sender}
+                            },
+                            (ClientMessage::GetSnapshot, Some(pid), _, Some(gid)) => {
+                                GameCommand::Snapshot { game_id: gid, player_id: pid, reply_sender }
+                            },
+                            (ClientMessage::ListRooms, _, _, _) => {
+                                GameCommand::ListRooms { reply_sender}
+                            },
+                            (ClientMessage::FindMatch { mode, word_length }, Some(pid), Some(profile), _) => {
+                                let settings = GameSettings { mode, word_length: word_length.unwrap_or(dict::WORD_LENGTH), ..GameSettings::default() };
+                                if let Err(error) = settings.validate() {
+                                    send_error(&mut tw, encoding, error, request_id.clone()).await;
+                                    continue;
+                                }
+                                let rating = match matchmaking::load_rating(&storage, &user_id).await {
+                                    Ok(rating) => rating,
+                                    Err(err) => {
+                                        error!("Failed to load rating: {err}");
+                                        matchmaking::DEFAULT_RATING
+                                    }
+                                };
+                                searching_match = true;
+                                GameCommand::FindMatch { player_id: pid, user_id, profile, settings, rating, reply_sender}
+                            },
+                            (ClientMessage::CancelMatch, Some(pid), _, _) => {
+                                GameCommand::CancelMatch { player_id: pid }
+                            },
+                            (_, None, _, _) | (_, _, None, _) => {
+                                send_error(&mut tw, encoding, not_connected(), request_id).await;
+                                continue;
+                            }
+                            (_, _, _, None) => {
+                                let error = GameError::NotInGame { message: "Join a game first".to_string() };
+                                send_error(&mut tw, encoding, error, request_id).await;
+                                continue;
+                            }
                         };
 
+                        // Nothing answers these, so they are acknowledged once they are on their way.
+                        let unanswered = matches!(command, GameCommand::Disconnect { .. } | GameCommand::CancelMatch { .. });
                         if let Err(err) = tx.send(command).await {
                             error!("Unable to send message to game coordinator {err}");
                             break;
                         }
+                        if unanswered {
+                            send_ack(&mut tw, encoding, request_id).await;
+                        }
                     },
                     Message::Close(frame) => {
                         tracing::info!("Client initiated graceful close. Frame: {:?}", frame);
@@ -157,7 +574,84 @@ pub async fn handle_socket(socket: WebSocket, tx: mpsc::Sender<GameCommand>) {
         }
     }
 
-    if let (Some(player_id), Some(game_id)) = (session_player_id.clone(), session_game_id.clone()) {
-        let _ = tx.send(GameCommand::Disconnect { game_id, player_id }).await;
+    if let (Some(player_id), true) = (session_player_id.clone(), searching_match) {
+        let _ = tx.send(GameCommand::CancelMatch { player_id }).await;
+    }
+    if let (Some(player_id), Some(game_id)) = (session_player_id.clone(), session_game_id.or(pending_game_id)) {
+        let _ = tx.send(GameCommand::ConnectionLost { game_id, player_id }).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_text(text: &str) -> Result<ClientRequest, (Option<RequestId>, GameError)> {
+        parse_request(protocol::decode(&Message::Text(text.into())))
+    }
+
+    #[test]
+    fn client_actions_match_the_client_messages() {
+        // serde lists every variant it expects when it meets an unknown one.
+        let err = serde_json::from_str::<ClientMessage>(r#"{"action":"?"}"#)
+            .unwrap_err()
+            .to_string();
+        let (_, expected) = err.split_once("expected one of ").unwrap();
+        let mut actions: Vec<&str> = expected
+            .split(", ")
+            .map(|action| action.split('`').nth(1).unwrap())
+            .collect();
+        let mut client_actions = CLIENT_ACTIONS.to_vec();
+        actions.sort_unstable();
+        client_actions.sort_unstable();
+        assert_eq!(actions, client_actions);
+    }
+
+    #[test]
+    fn requests_keep_their_id() {
+        let request = parse_text(r#"{"action":"guessWord","word":"crane","requestId":"7"}"#).unwrap();
+        assert_eq!(request.request_id.as_deref(), Some("7"));
+        assert!(matches!(request.message, ClientMessage::GuessWord { word } if word == "crane"));
+    }
+
+    #[test]
+    fn unknown_actions_are_told_apart_from_bad_payloads() {
+        let Err((request_id, error)) = parse_text(r#"{"action":"fly","requestId":"1"}"#) else {
+            panic!("fly is not an action");
+        };
+        assert_eq!(request_id.as_deref(), Some("1"));
+        assert!(matches!(error, GameError::UnknownAction { action, .. } if action == "fly"));
+
+        let Err((request_id, error)) = parse_text(r#"{"action":"guessWord","word":5,"requestId":"2"}"#) else {
+            panic!("word must be a string");
+        };
+        assert_eq!(request_id.as_deref(), Some("2"));
+        assert!(matches!(error, GameError::InvalidMessage { .. }));
+    }
+
+    #[test]
+    fn messages_without_an_action_or_json_are_invalid() {
+        assert!(matches!(
+            parse_text(r#"{"requestId":"3"}"#),
+            Err((Some(_), GameError::InvalidMessage { .. }))
+        ));
+        assert!(matches!(
+            parse_text("not json"),
+            Err((None, GameError::InvalidMessage { .. }))
+        ));
+    }
+
+    #[test]
+    fn binary_frames_are_read_as_messagepack() {
+        let value = serde_json::json!({ "action": "guessWord", "word": "crane", "requestId": "4" });
+        let bytes = rmp_serde::to_vec_named(&value).unwrap();
+        let request = parse_request(protocol::decode(&Message::Binary(bytes.into()))).unwrap();
+        assert_eq!(request.request_id.as_deref(), Some("4"));
+        assert!(matches!(request.message, ClientMessage::GuessWord { word } if word == "crane"));
+
+        assert!(matches!(
+            parse_request(protocol::decode(&Message::Binary(vec![0xc1].into()))),
+            Err((None, GameError::InvalidMessage { .. }))
+        ));
     }
 }
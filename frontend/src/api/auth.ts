@@ -0,0 +1,43 @@
+const API_URL: string = import.meta.env.VITE_API_URL;
+
+export interface Account {
+	userId: string,
+	username: string,
+	token: string,
+}
+
+type AuthResult = { ok: true, account: Account } | { ok: false, message: string };
+
+const authenticate = async (path: "register" | "login", username: string, password: string): Promise<AuthResult> => {
+	try {
+		const response = await fetch(`${API_URL}/auth/${path}`, {
+			method: "POST",
+			headers: { "Content-Type": "application/json" },
+			body: JSON.stringify({ username, password }),
+		});
+		const body = await response.json();
+		if (!response.ok) {
+			return { ok: false, message: body.message };
+		}
+		localStorage.setItem("authToken", body.token);
+		localStorage.setItem("username", body.username);
+		return { ok: true, account: body };
+	} catch (e) {
+		console.log(e);
+		return { ok: false, message: "Unable to reach server" };
+	}
+}
+
+export const register = (username: string, password: string) => authenticate("register", username, password);
+export const login = (username: string, password: string) => authenticate("login", username, password);
+
+export const logout = () => {
+	localStorage.removeItem("authToken");
+	localStorage.removeItem("username");
+	localStorage.removeItem("userToken");
+}
+
+export const websocketUrl = (baseUrl: string) => {
+	const token = localStorage.getItem("authToken");
+	return token ? `${baseUrl}?token=${encodeURIComponent(token)}` : baseUrl;
+}
@@ -1,7 +1,9 @@
 import { createSignal, onMount } from "solid-js";
 import { setGameStore, gameStore, resetGame } from "../store/gameStore";
 import { onCleanup } from "solid-js";
-import type { ClientMessage, ServerMessage, Ready, SendMessage, WebsocketState, Error } from "../types";
+import type { BoardChange, ClientMessage, ClientRequest, ServerMessage, Ready, SendMessage, WebsocketState, Error } from "../types";
+import { PROTOCOL_VERSION } from "../types/protocol.ts";
+import { websocketUrl } from "./auth";
 
 const WEBSOCKET_URL: string = import.meta.env.VITE_WEBSOCKET_URL;
 const MAX_ATTEMPTS: number = import.meta.env.VITE_MAX_RECONNECT_ATTEMPTS;
@@ -13,6 +15,9 @@ const createWebsocket = (): WebsocketState => {
 	const [readyState, setReadyState] = createSignal<Ready>("CONNECTING");
 
 	let retryTimeout: number | null = null;
+	let nextRequestId = 0;
+	// Actions still waiting for their ack, by request id.
+	const pending = new Map<string, ClientMessage["action"]>();
 	let manualClose = false;
 	const readyMap: Record<number, Ready> = { 0: "CONNECTING", 1: "OPEN", 2: "CLOSING", 3: "CLOSED" };
 
@@ -21,6 +26,34 @@ const createWebsocket = (): WebsocketState => {
 		setGameStore("toasts", toasts => [...toasts, { id, error }].slice(-3));
 	}
 
+	// Deltas only apply on top of the version right before theirs, after a gap the whole board is fetched again.
+	const acceptVersion = (version: number) => {
+		if (version <= gameStore.version) return false;
+		if (version > gameStore.version + 1) {
+			sendMessage({ action: "getSnapshot" });
+			return false;
+		}
+		setGameStore("version", version);
+		return true;
+	}
+
+	const applyChange = (change: BoardChange) => {
+		switch (change.kind) {
+			case "guessAdded":
+				setGameStore("guesses", guesses => [...guesses, change.guess]);
+				break;
+			case "keysUpdated":
+				setGameStore("keyboardStatus", keys => ({ ...keys, ...change.keys }));
+				break;
+			case "turnChanged":
+				setGameStore({ currentTurn: change.currentTurn, draft: "", suggestions: [] });
+				break;
+			case "statusChanged":
+				setGameStore({ gameStatus: change.gameStatus, solution: change.solution });
+				break;
+		}
+	}
+
 	const connectWebsocket = () => {
 		manualClose = false;
 		const currentWs = ws();
@@ -28,18 +61,21 @@ const createWebsocket = (): WebsocketState => {
 			return;
 		}
 
-		const newWs = new WebSocket(WEBSOCKET_URL);
+		const newWs = new WebSocket(websocketUrl(WEBSOCKET_URL));
 		setWs(newWs);
 
 		newWs.onopen = () => {
 			setRetries(0);
 			setReadyState(readyMap[ws()?.readyState ?? 3]);
-			const sessionPlayerId = sessionStorage.getItem("oldPlayerId");
+			const resumeToken = sessionStorage.getItem("resumeToken");
+			const userToken = localStorage.getItem("userToken") ?? undefined;
 
 			const connectMessage: ClientMessage = {
 				action: "connect",
-				gameId: gameStore.gameId,
-				oldPlayerId: sessionPlayerId,
+				gameId: gameStore.gameId ?? sessionStorage.getItem("gameId"),
+				resumeToken,
+				userToken,
+				protocolVersion: PROTOCOL_VERSION,
 			}
 			sendMessage(connectMessage);
 		};
@@ -55,49 +91,135 @@ const createWebsocket = (): WebsocketState => {
 
 			switch (data.status) {
 				case "welcome":
-					setGameStore({ playerId: data.playerId });
-					sessionStorage.setItem("oldPlayerId", data.playerId);
+					setGameStore({ playerId: data.playerId, handle: data.handle });
+					localStorage.setItem("userToken", data.userToken);
+					break;
+				case "resumeToken":
+					sessionStorage.setItem("resumeToken", data.resumeToken);
 					break;
 				case "created":
+					sessionStorage.setItem("gameId", data.gameId);
 					setGameStore({ gameId: data.gameId, gameStatus: data.gameStatus });
 					break;
 				case "joined":
+					sessionStorage.setItem("gameId", data.gameId);
 					setGameStore({
 						gameId: data.gameId,
+						matchPosition: null,
 						currentTurn: data.boardState.currentTurn,
 						guesses: data.boardState.guesses,
 						gameStatus: data.boardState.gameStatus,
 						keyboardStatus: data.boardState.keyboardStatus,
 						players: data.boardState.players,
 						solution: data.solution,
+						version: data.boardState.version,
 					});
 					break;
 				case "gameUpdate":
 					setGameStore({
+						version: data.boardState.version,
 						currentTurn: data.boardState.currentTurn,
 						solution: data.solution,
 						guesses: data.boardState.guesses,
 						gameStatus: data.boardState.gameStatus,
 						keyboardStatus: data.boardState.keyboardStatus,
 						players: data.boardState.players,
+						draft: "",
+						suggestions: [],
 					});
 					break;
+				case "ack":
+					pending.delete(data.requestId);
+					break;
 				case "error":
-					if (data.error.type == "joinError") {
+					// Only ever used between the server's own tasks.
+					if (data.error.type == "stopGame") break;
+					if (data.requestId) {
+						console.log(`${pending.get(data.requestId)} failed: ${data.error.message}`);
+						pending.delete(data.requestId);
+					}
+					if (["joinError", "passwordError", "invalidResumeToken", "expiredResumeToken"].includes(data.error.type)) {
 						resetGame();
 					}
+					if (data.error.type == "unsupportedProtocol") {
+						// Reconnecting won't help, this build needs a reload.
+						manualClose = true;
+						addToast({ type: "unsupportedProtocol", message: "A new version is available, please reload the page" });
+						break;
+					}
 					addToast(data.error);
 					break;
 				case "newGame":
 				case "exited":
 					setGameStore({
+						version: data.boardState.version,
 						currentTurn: data.boardState.currentTurn,
 						guesses: data.boardState.guesses,
 						gameStatus: data.boardState.gameStatus,
 						keyboardStatus: data.boardState.keyboardStatus,
 						players: data.boardState.players,
+						draft: "",
+						suggestions: [],
 					});
 					break;
+				case "serverShuttingDown":
+					addToast({ type: "joinError", message: `Server restarting, back in ${data.reconnectAfter}s` });
+					break;
+				case "roomClosed":
+					// Rooms stopped for a restart are rejoined on reconnect, so keep what's needed to find them again.
+					if (!data.resumable) resetGame();
+					addToast({ type: "joinError", message: data.reason });
+					break;
+				case "matchQueued":
+					setGameStore({ matchPosition: data.position });
+					break;
+				case "matchCancelled":
+					setGameStore({ matchPosition: null });
+					break;
+				case "matchTimedOut":
+					setGameStore({ matchPosition: null });
+					addToast({ type: "joinError", message: "No match found, try again" });
+					break;
+				case "roomList":
+					setGameStore({ rooms: data.rooms });
+					break;
+				case "stats":
+					setGameStore({ stats: data.stats });
+					break;
+				case "leaderboard":
+					setGameStore({ leaderboard: data.entries });
+					break;
+				case "boardDelta":
+					if (!acceptVersion(data.version)) break;
+					data.changes.forEach(applyChange);
+					break;
+				case "reaction":
+					if (!acceptVersion(data.version)) break;
+					if (data.guessIndex >= gameStore.guesses.length) break;
+					setGameStore("guesses", data.guessIndex, "reactions", reactions => ({ ...reactions, [data.emoji]: data.count }));
+					break;
+				case "draft":
+					if (data.from == gameStore.currentTurn) {
+						setGameStore("draft", data.letters);
+					}
+					break;
+				case "suggestion":
+					setGameStore("suggestions", suggestions => [
+						...suggestions.filter(suggestion => suggestion.from != data.from),
+						{ from: data.from, word: data.word },
+					]);
+					break;
+				case "chatMessage": {
+					const { from, text, ts } = data;
+					// History is replayed on every join, skip what we already have.
+					if (gameStore.chat.some(entry => entry.ts == ts && entry.from == from && entry.text == text)) break;
+					setGameStore("chat", chat => [...chat, { from, text, ts }].slice(-50));
+					break;
+				}
+				case "kicked":
+					resetGame();
+					addToast({ type: "joinError", message: "You were removed from the room by the host" });
+					break;
 				default:
 					console.log("^^ invalid data type");
 			}
@@ -105,6 +227,7 @@ const createWebsocket = (): WebsocketState => {
 
 		newWs.onclose = () => {
 			setWs(null);
+			pending.clear();
 			if (manualClose) return;
 			const attempts = retries();
 			if (attempts >= MAX_ATTEMPTS) {
@@ -129,7 +252,10 @@ const createWebsocket = (): WebsocketState => {
 			return;
 		}
 		try {
-			const messageJson = JSON.stringify(message);
+			const requestId = String(++nextRequestId);
+			const request: ClientRequest = { ...message, requestId };
+			const messageJson = JSON.stringify(request);
+			if (message.action != "draftUpdate") pending.set(requestId, message.action);
 			currentWs.send(messageJson);
 		} catch (e) {
 			console.log(e);
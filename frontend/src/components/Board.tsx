@@ -3,6 +3,9 @@ import { For, onCleanup, createMemo, createEffect, type ParentComponent, onMount
 import { gameStore } from "../store/gameStore";
 import type { Guess, KeyColor } from "../types";
 import { useGuess } from "../context/guessContext";
+import { useWebsocket } from "../context/websocketContext";
+
+const REACTION_EMOJIS = ["👏", "🔥", "😮", "😂", "🤔", "💀"];
 
 const Board = () => {
 	const { currentGuess, actions } = useGuess();
@@ -13,7 +16,8 @@ const Board = () => {
 	const guessRows = createMemo(() =>
 		gameStore.guesses.map(guess => ({
 			word: guess.word,
-			status: guess.status ?? []
+			status: guess.status ?? [],
+			reactions: guess.reactions ?? {},
 		} as Guess))
 	);
 
@@ -21,7 +25,7 @@ const Board = () => {
 		if (gameStore.guesses.length >= 6) {
 			return [];
 		}
-		const currentRow = currentGuess();
+		const currentRow = gameStore.currentTurn == gameStore.handle ? currentGuess() : gameStore.draft;
 		const paddingRows = emptyRows.slice(0, 5 - gameStore.guesses.length);
 		return [currentRow, ...paddingRows];
 	})
@@ -34,6 +38,7 @@ const Board = () => {
 
 	const handleKeyPress = (event: KeyboardEvent) => {
 		if (event.repeat) return;
+		if (event.target instanceof HTMLInputElement) return;
 		if (gameStore.players.length < 2) return;
 		if (gameStore.gameStatus != "inProgress") return;
 
@@ -63,19 +68,22 @@ const Board = () => {
 				{(guess, index) => {
 					const shouldFlip = index() == gameStore.guesses.length - 1;
 					return (
-						<BoardRow flip={shouldFlip}>
-							<For each={indices}>
-								{(_, charIndex) =>
-									<BoardTile
-										letter={guess.word[charIndex()]}
-										state={guess.status[charIndex()]}
-										index={charIndex()}
-										flip={shouldFlip}
-										pop={false}
-									/>
-								}
-							</For>
-						</BoardRow>
+						<div class="relative group">
+							<BoardRow flip={shouldFlip}>
+								<For each={indices}>
+									{(_, charIndex) =>
+										<BoardTile
+											letter={guess.word[charIndex()]}
+											state={guess.status[charIndex()]}
+											index={charIndex()}
+											flip={shouldFlip}
+											pop={false}
+										/>
+									}
+								</For>
+							</BoardRow>
+							<Reactions guessIndex={index()} reactions={guess.reactions} />
+						</div>
 					)
 				}}
 			</For>
@@ -102,6 +110,29 @@ const Board = () => {
 	)
 }
 
+const Reactions = (props: { guessIndex: number, reactions: Guess["reactions"] }) => {
+	const { sendMessage } = useWebsocket();
+
+	return (
+		<div class="absolute left-full top-0 h-full ml-2 flex items-center gap-1 text-sm">
+			<For each={REACTION_EMOJIS}>
+				{(emoji) => {
+					const count = () => props.reactions[emoji] ?? 0;
+					return (
+						<button type="button"
+							onClick={() => sendMessage({ action: "react", guessIndex: props.guessIndex, emoji })}
+							class="cursor-pointer rounded-md px-1 bg-background"
+							classList={{ "hidden group-hover:block": count() == 0 }}
+						>
+							{emoji}{count() > 0 ? count() : ""}
+						</button>
+					)
+				}}
+			</For>
+		</div>
+	)
+}
+
 const BoardRow: ParentComponent<{ flip: boolean }> = (props) => {
 	return (
 		<div class="grid grid-cols-5 gap-1 sm:gap-2 w-full"
@@ -144,7 +175,7 @@ const BoardTile = (props: TileProps) => {
 				classList={{
 					"border-light-gray": props.flip,
 					[data.tailwind]: !props.flip,
-					"text-light-gray": (gameStore.currentTurn != gameStore.playerId && props.state == "default"),
+					"text-light-gray": (gameStore.currentTurn != gameStore.handle && props.state == "default"),
 				}}
 			>
 				{props.letter}
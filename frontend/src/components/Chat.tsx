@@ -0,0 +1,46 @@
+import { createSignal, For, Show } from "solid-js";
+import { useWebsocket } from "../context/websocketContext";
+import { gameStore } from "../store/gameStore";
+
+const MAX_CHAT_LENGTH = 200;
+
+const Chat = () => {
+	const { sendMessage } = useWebsocket();
+	const [text, setText] = createSignal("");
+	const [open, setOpen] = createSignal(false);
+
+	const nameOf = (handle: string) =>
+		gameStore.players.find(player => player.handle == handle)?.displayName ?? handle;
+
+	const send = (event: SubmitEvent) => {
+		event.preventDefault();
+		if (text().trim().length == 0) return;
+		sendMessage({ action: "chat", text: text() });
+		setText("");
+	}
+
+	return (
+		<div class="fixed bottom-2 right-2 w-72 text-text text-left z-30">
+			<button type="button" onClick={() => setOpen(!open())} class="bg-background shadow-s rounded-md px-3 py-1 cursor-pointer font-bold">
+				Chat ({gameStore.chat.length})
+			</button>
+			<Show when={open()}>
+				<div class="bg-background shadow-s rounded-xl p-3 mt-2 space-y-2">
+					<div class="h-48 overflow-y-auto space-y-1 text-sm">
+						<For each={gameStore.chat}>
+							{(entry) => (
+								<p><span class="font-bold">{nameOf(entry.from)}:</span> {entry.text}</p>
+							)}
+						</For>
+					</div>
+					<form onSubmit={send} class="flex gap-2">
+						<input class="bg-background-light rounded-md outline-none p-1 grow" maxLength={MAX_CHAT_LENGTH} value={text()} onInput={(event) => setText(event.target.value)} type="text" />
+						<button type="submit" class="bg-background-light hover:bg-green-800 rounded-md px-2 cursor-pointer transition-colors duration-200">Send</button>
+					</form>
+				</div>
+			</Show>
+		</div>
+	)
+}
+
+export default Chat;
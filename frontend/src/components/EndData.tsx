@@ -1,4 +1,5 @@
 import { useNavigate } from "@solidjs/router";
+import { onMount, Show } from "solid-js";
 import { useWebsocket } from "../context/websocketContext";
 import "../main.css";
 import { gameStore, resetGame } from "../store/gameStore";
@@ -8,6 +9,8 @@ const EndData = () => {
 	const { sendMessage } = useWebsocket();
 	const navigate = useNavigate();
 
+	onMount(() => sendMessage({ action: "getStats" }));
+
 	const sendNewGame = () => {
 		if (!gameStore.gameId) return;
 		const newGameMessage: ClientMessage = { action: "newGame" };
@@ -31,6 +34,16 @@ const EndData = () => {
 					<p class="text-nowrap">You {gameStore.gameStatus} to:</p>
 					<p class="bg-background-light sm:bg-dark-gray grow py-2 px-4 rounded-md text-center">{gameStore.solution}</p>
 				</div>
+				<Show when={gameStore.stats}>
+					{(stats) => (
+						<div class="grid grid-cols-4 text-center text-sm">
+							<p><span class="block text-lg font-bold">{stats().gamesPlayed}</span>Played</p>
+							<p><span class="block text-lg font-bold">{stats().winPercentage}</span>Win %</p>
+							<p><span class="block text-lg font-bold">{stats().currentStreak}</span>Streak</p>
+							<p><span class="block text-lg font-bold">{stats().maxStreak}</span>Max</p>
+						</div>
+					)}
+				</Show>
 				<div class="flex gap-2">
 					<button type="button" onClick={sendNewGame} class="w-full text-nowrap grow bg-background-light sm:bg-dark-gray py-2 px-4 rounded-md hover:bg-green-800 cursor-pointer transition-colors duration-200">New Game</button>
 					<button type="button" onClick={disconnect} class="bg-background-light sm:bg-dark-gray py-2 px-4 rounded-md hover:bg-yellow-600 cursor-pointer transition-colors duration-200">Exit</button>
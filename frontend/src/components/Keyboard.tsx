@@ -62,8 +62,8 @@ const KeyboardTile = (props: { key: string }) => {
 			classList={{
 				"w-16": bigKey,
 				"w-10": !bigKey,
-				"bg-dark-gray": props.key == "Enter" && gameStore.currentTurn != gameStore.playerId,
-				[animationColor()]: props.key != "Enter" || gameStore.currentTurn == gameStore.playerId,
+				"bg-dark-gray": props.key == "Enter" && gameStore.currentTurn != gameStore.handle,
+				[animationColor()]: props.key != "Enter" || gameStore.currentTurn == gameStore.handle,
 			}}
 			class="cursor-pointer h-14 flex justify-center items-center text-xl font-extrabold rounded-sm transition-colors delay-[1.4s]"
 			disabled={gameStore.gameStatus != "inProgress"}
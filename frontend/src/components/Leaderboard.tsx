@@ -0,0 +1,67 @@
+import { createEffect, createSignal, For, Show } from "solid-js";
+import { useWebsocket } from "../context/websocketContext";
+import { gameStore } from "../store/gameStore";
+import type { GameMode, LeaderboardKind, LeaderboardWindow } from "../types";
+
+const boards: { kind: LeaderboardKind, label: string }[] = [
+	{ kind: "longestStreak", label: "Streak" },
+	{ kind: "averageGuesses", label: "Avg guesses" },
+	{ kind: "rating", label: "Rating" },
+];
+
+const modes: { mode: GameMode | null, label: string }[] = [
+	{ mode: null, label: "All" },
+	{ mode: "daily", label: "Daily" },
+	{ mode: "competitive", label: "Versus" },
+];
+
+const Leaderboard = () => {
+	const { sendMessage } = useWebsocket();
+	const [board, setBoard] = createSignal<LeaderboardKind>("longestStreak");
+	const [window, setWindow] = createSignal<LeaderboardWindow>("weekly");
+	const [mode, setMode] = createSignal<GameMode | null>(null);
+
+	createEffect(() => {
+		if (gameStore.playerId == null) return;
+		sendMessage({ action: "getLeaderboard", board: board(), window: window(), mode: mode() });
+	});
+
+	return (
+		<div class="p-5 bg-background rounded-xl text-text shadow-s space-y-2 w-72">
+			<div class="flex justify-between font-bold">
+				<p>Leaderboard</p>
+				<button type="button" class="cursor-pointer" onClick={() => setWindow(window() == "weekly" ? "allTime" : "weekly")}>
+					{window() == "weekly" ? "This week" : "All time"}
+				</button>
+			</div>
+			<div class="flex gap-2 text-sm">
+				<For each={boards}>
+					{({ kind, label }) => (
+						<button type="button" onClick={() => setBoard(kind)} class={`${board() == kind ? "bg-green-800" : "bg-background-light"} w-full rounded-md cursor-pointer p-1 transition-colors duration-200`}>{label}</button>
+					)}
+				</For>
+			</div>
+			<Show when={board() != "rating"}>
+				<div class="flex gap-2 text-sm">
+					<For each={modes}>
+						{(option) => (
+							<button type="button" onClick={() => setMode(option.mode)} class={`${mode() == option.mode ? "bg-green-800" : "bg-background-light"} w-full rounded-md cursor-pointer p-1 transition-colors duration-200`}>{option.label}</button>
+						)}
+					</For>
+				</div>
+			</Show>
+			<Show when={gameStore.leaderboard.length > 0} fallback={<p class="text-sm text-center">No results yet</p>}>
+				<For each={gameStore.leaderboard}>
+					{(entry) => (
+						<div class="flex justify-between text-sm">
+							<span>{entry.rank}. {entry.username}</span>
+							<span class="font-bold">{board() == "averageGuesses" ? entry.value.toFixed(2) : Math.round(entry.value)}</span>
+						</div>
+					)}
+				</For>
+			</Show>
+		</div>
+	)
+}
+
+export default Leaderboard;
@@ -0,0 +1,45 @@
+import { createSignal, For, Show } from "solid-js";
+import { useWebsocket } from "../context/websocketContext";
+import { gameStore } from "../store/gameStore";
+
+const Suggestions = () => {
+	const { sendMessage } = useWebsocket();
+	const [word, setWord] = createSignal("");
+
+	const myTurn = () => gameStore.currentTurn == gameStore.handle;
+	const nameOf = (handle: string) =>
+		gameStore.players.find(player => player.handle == handle)?.displayName ?? handle;
+
+	const suggest = (event: SubmitEvent) => {
+		event.preventDefault();
+		if (word().trim().length == 0) return;
+		sendMessage({ action: "suggestWord", word: word().trim() });
+		setWord("");
+	}
+
+	return (
+		<Show when={gameStore.gameStatus == "inProgress"}>
+			<div class="text-text text-sm flex flex-wrap justify-center gap-2">
+				<Show when={myTurn()} fallback={
+					<form onSubmit={suggest} class="flex gap-2">
+						<input class="bg-background-light rounded-md outline-none p-1 w-28 uppercase" maxLength={5} placeholder="Suggest" value={word()} onInput={(event) => setWord(event.target.value)} type="text" />
+						<button type="submit" class="bg-background hover:bg-green-800 rounded-md px-2 cursor-pointer transition-colors duration-200">Send</button>
+					</form>
+				}>
+					<For each={gameStore.suggestions}>
+						{(suggestion) => (
+							<button type="button"
+								onClick={() => sendMessage({ action: "acceptSuggestion", from: suggestion.from })}
+								class="bg-background hover:bg-green-800 rounded-md px-2 py-1 cursor-pointer transition-colors duration-200"
+							>
+								{nameOf(suggestion.from)}: <span class="font-bold">{suggestion.word}</span>
+							</button>
+						)}
+					</For>
+				</Show>
+			</div>
+		</Show>
+	)
+}
+
+export default Suggestions;
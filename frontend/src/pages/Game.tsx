@@ -9,6 +9,8 @@ import EndData from "../components/EndData";
 import NavBar from "../components/NavBar";
 import GuessProvider from "../providers/guessProvider";
 import Toast from "../components/Toast";
+import Chat from "../components/Chat";
+import Suggestions from "../components/Suggestions";
 
 const Game = () => {
 
@@ -45,10 +47,12 @@ const Game = () => {
 					</Show>
 					<GuessProvider>
 						<Board />
+						<Suggestions />
 						<Keyboard />
 					</GuessProvider>
 				</div>
 			</Show>
+			<Chat />
 		</main>
 	)
 }
@@ -1,13 +1,21 @@
 import "../main.css";
-import { createSignal, createEffect } from "solid-js";
+import { createSignal, createEffect, For, Show } from "solid-js";
 import { useWebsocket } from "../context/websocketContext";
 import type { ClientMessage } from "../types";
 import { gameStore } from "../store/gameStore";
 import { useNavigate, useSearchParams } from "@solidjs/router";
 import Toast from "../components/Toast";
+import Leaderboard from "../components/Leaderboard";
+import { login, logout, register } from "../api/auth";
 
 const Home = () => {
 	const [inputId, setInputId] = createSignal<string>("")
+	const [password, setPassword] = createSignal<string>("")
+	const [isPublic, setIsPublic] = createSignal<boolean>(false)
+	const [username, setUsername] = createSignal<string>("")
+	const [accountPassword, setAccountPassword] = createSignal<string>("")
+	const [authError, setAuthError] = createSignal<string | null>(null)
+	const signedInAs = localStorage.getItem("username");
 	const { sendMessage } = useWebsocket();
 
 	const navigate = useNavigate();
@@ -19,6 +27,11 @@ const Home = () => {
 		joinGame(id);
 	});
 
+	createEffect(() => {
+		if (gameStore.playerId == null) return;
+		sendMessage({ action: "listRooms" });
+	});
+
 	createEffect(() => {
 		if (gameStore.gameStatus != "pending") {
 			if (!document.startViewTransition) {
@@ -34,31 +47,100 @@ const Home = () => {
 			return;
 		}
 
-		const createMessage: ClientMessage = { action: "createGame" };
+		const createMessage: ClientMessage = { action: "createGame", password: password() || undefined, public: isPublic() };
 		sendMessage(createMessage);
 	}
 
+	const findMatch = () => {
+		if (gameStore.playerId == null) {
+			return;
+		}
+		if (gameStore.matchPosition != null) {
+			sendMessage({ action: "cancelMatch" });
+			return;
+		}
+		sendMessage({ action: "findMatch", mode: "coop" });
+	}
+
 	const joinGame = (id: string | null) => {
 		const trueId = id ?? inputId();
-		if (gameStore.playerId == null || trueId.length != 5) {
+		if (gameStore.playerId == null || trueId.length == 0) {
 			return;
 		}
 
-		const joinMessage: ClientMessage = { action: "joinGame", gameId: trueId };
+		const joinMessage: ClientMessage = { action: "joinGame", gameId: trueId, password: password() || undefined };
 		sendMessage(joinMessage);
 	}
 
+	const authenticate = async (action: typeof login) => {
+		const result = await action(username(), accountPassword());
+		if (!result.ok) {
+			setAuthError(result.message);
+			return;
+		}
+		// The socket only picks up the account when it connects.
+		location.reload();
+	}
+
+	const signOut = () => {
+		logout();
+		location.reload();
+	}
+
 	return (
 		<main class="relative overflow-hidden bg-background-dark w-full h-dvh flex flex-col gap-5 justify-center items-center">
 			<Toast />
 			<p class="text-4xl font-extrabold text-text" style={{ "view-transition-name": "title" }}>CoWordle</p>
 			<div class="p-5 bg-background rounded-xl text-center shadow-s text-text font-bold space-y-2">
 				<button type="button" onClick={createGame} class="bg-background-light hover:bg-green-800 w-full rounded-md cursor-pointer p-2 transition-colors duration-200">Create Game</button>
+				<button type="button" onClick={findMatch} class="bg-background-light hover:bg-green-800 w-full rounded-md cursor-pointer p-2 transition-colors duration-200">
+					{gameStore.matchPosition == null ? "Quick Match" : `Searching (#${gameStore.matchPosition}), cancel`}
+				</button>
 				<div class="w-full flex gap-2">
 					<input class="bg-background-light rounded-md uppercase outline-none text-center" onInput={(event) => setInputId(event.target.value.toUpperCase())} type="text" />
 					<button class="bg-background-light hover:bg-yellow-600 cursor-pointer rounded-md p-2 transition-colors duration-200" onClick={() => joinGame(null)}>Join</button>
 				</div>
+				<input class="bg-background-light rounded-md outline-none text-center w-full p-1" placeholder="Room password (optional)" onInput={(event) => setPassword(event.target.value)} type="password" />
+				<label class="flex gap-2 justify-center items-center font-normal cursor-pointer">
+					<input type="checkbox" checked={isPublic()} onChange={(event) => setIsPublic(event.target.checked)} />
+					List my room in the lobby
+				</label>
 			</div>
+			<div class="p-5 bg-background rounded-xl text-center shadow-s text-text space-y-2 w-72">
+				<Show when={signedInAs} fallback={
+					<>
+						<input class="bg-background-light rounded-md outline-none text-center w-full p-1" placeholder="Username" onInput={(event) => setUsername(event.target.value)} type="text" />
+						<input class="bg-background-light rounded-md outline-none text-center w-full p-1" placeholder="Password" onInput={(event) => setAccountPassword(event.target.value)} type="password" />
+						<div class="flex gap-2 font-bold">
+							<button type="button" onClick={() => authenticate(login)} class="bg-background-light hover:bg-green-800 w-full rounded-md cursor-pointer p-2 transition-colors duration-200">Sign in</button>
+							<button type="button" onClick={() => authenticate(register)} class="bg-background-light hover:bg-yellow-600 w-full rounded-md cursor-pointer p-2 transition-colors duration-200">Register</button>
+						</div>
+						<Show when={authError()}>
+							<p class="text-sm">{authError()}</p>
+						</Show>
+					</>
+				}>
+					<p>Signed in as <span class="font-bold">{signedInAs}</span></p>
+					<button type="button" onClick={signOut} class="bg-background-light hover:bg-red-800 w-full rounded-md cursor-pointer p-2 font-bold transition-colors duration-200">Sign out</button>
+				</Show>
+			</div>
+			<Show when={gameStore.rooms.length > 0}>
+				<div class="p-5 bg-background rounded-xl text-text shadow-s space-y-2 w-72">
+					<div class="flex justify-between font-bold">
+						<p>Open rooms</p>
+						<button type="button" class="cursor-pointer" onClick={() => sendMessage({ action: "listRooms" })}>Refresh</button>
+					</div>
+					<For each={gameStore.rooms}>
+						{(room) => (
+							<button type="button" onClick={() => joinGame(room.gameId)} class="bg-background-light hover:bg-yellow-600 w-full rounded-md cursor-pointer p-2 flex justify-between transition-colors duration-200">
+								<span class="font-bold">{room.gameId}{room.hasPassword ? " 🔒" : ""}</span>
+								<span>{room.mode} · {room.playerCount}/2</span>
+							</button>
+						)}
+					</For>
+				</div>
+			</Show>
+			<Leaderboard />
 		</main>
 	)
 }
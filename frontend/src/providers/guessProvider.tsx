@@ -1,4 +1,4 @@
-import { createSignal, type JSXElement } from "solid-js";
+import { createEffect, createSignal, on, type JSXElement } from "solid-js";
 import { guessContext } from "../context/guessContext";
 import { useWebsocket } from "../context/websocketContext";
 import { gameStore, setGameStore } from "../store/gameStore";
@@ -8,6 +8,11 @@ const GuessProvider = (props: { children: JSXElement }) => {
 	const { sendMessage } = useWebsocket();
 	const [currentGuess, setCurrentGuess] = createSignal("");
 
+	createEffect(on(currentGuess, letters => {
+		if (gameStore.currentTurn != gameStore.handle || gameStore.gameStatus != "inProgress") return;
+		sendMessage({ action: "draftUpdate", letters });
+	}, { defer: true }));
+
 	const actions = {
 		addLetter(key: string) {
 			if (currentGuess().length < 5) {
@@ -24,7 +29,7 @@ const GuessProvider = (props: { children: JSXElement }) => {
 		},
 		sendGuess() {
 			if (!gameStore.playerId || !gameStore.gameId) return;
-			if (gameStore.currentTurn != gameStore.playerId) {
+			if (gameStore.currentTurn != gameStore.handle) {
 				const id = Date.now();
 				const error: Error = {type: "guessError", message: "Not your turn to guess"};
 				setGameStore("toasts", toasts => [...toasts, { id, error }].slice(-3));
@@ -3,25 +3,43 @@ import type { GameStore } from "../types";
 
 const initialState: GameStore = {
 	playerId: null,
+	handle: null,
 	gameId: null,
+	version: 0,
 	solution: null,
 	currentTurn: "",
 	guesses: [],
 	gameStatus: "pending",
 	keyboardStatus: {},
 	players: [],
+	rooms: [],
+	matchPosition: null,
+	stats: null,
+	leaderboard: [],
+	chat: [],
+	draft: "",
+	suggestions: [],
 	toasts: [],
 };
 
 export const [gameStore, setGameStore] = createStore<GameStore>(initialState);
 
-export const resetGame = () => setGameStore({
-	gameId: null,
-	solution: null,
-	currentTurn: "",
-	guesses: [],
-	gameStatus: "pending",
-	keyboardStatus: {},
-	players: [],
-	toasts: [],
-});
+export const resetGame = () => {
+	sessionStorage.removeItem("gameId");
+	sessionStorage.removeItem("resumeToken");
+	setGameStore({
+		gameId: null,
+		version: 0,
+		solution: null,
+		currentTurn: "",
+		guesses: [],
+		gameStatus: "pending",
+		keyboardStatus: {},
+		players: [],
+		matchPosition: null,
+		chat: [],
+		draft: "",
+		suggestions: [],
+		toasts: [],
+	});
+};
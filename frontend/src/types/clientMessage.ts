@@ -1,7 +1,2 @@
-export type ClientMessage =
-	| { action: "connect", gameId: string | null, oldPlayerId: string | null}
-	| { action: "joinGame", gameId: string }
-	| { action: "guessWord", word: string }
-	| { action: "createGame" }
-	| { action: "newGame"}
-	| { action: "disconnectPlayer"};
+// Every message can carry an id, the server answers it with an `ack` or an `error` carrying the same id.
+export type { ClientMessage, ClientRequest } from "./protocol.ts";
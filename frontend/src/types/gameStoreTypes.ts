@@ -1,4 +1,4 @@
-import type { Guess, Error, GameState, KeyColor } from "./serverMessage";
+import type { BoardState, ChatEntry, Guess, Error, GameState, LeaderboardEntry, PlayerStats, PublicPlayer, RoomSummary, Suggestion } from "./serverMessage";
 
 interface Toast {
 	id: number,
@@ -7,12 +7,21 @@ interface Toast {
 
 export interface GameStore {
 	playerId: string | null,
+	handle: string | null,
 	gameId: string | null,
+	version: number,
 	solution: string | null,
 	currentTurn: string,
 	guesses: Guess[],
 	gameStatus: GameState,
-	keyboardStatus: { [key: string]: KeyColor; },
-	players: string[],
+	keyboardStatus: BoardState["keyboardStatus"],
+	players: PublicPlayer[],
+	rooms: RoomSummary[],
+	matchPosition: number | null,
+	stats: PlayerStats | null,
+	leaderboard: LeaderboardEntry[],
+	chat: ChatEntry[],
+	draft: string,
+	suggestions: Suggestion[],
 	toasts: Toast[],
 }
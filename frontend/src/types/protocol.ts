@@ -0,0 +1,93 @@
+// Generated from the backend protocol types by `cargo run -- protocol-types`, do not edit by hand.
+
+export type BoardChange = { "kind": "guessAdded", guess: GuessResult, } | { "kind": "keysUpdated", keys: { [key in string]?: GameColor }, } | { "kind": "turnChanged", currentTurn: string, } | { "kind": "statusChanged", gameStatus: GameStatus, solution: string | null, };
+
+export type BoardState = { 
+/**
+ * Goes up with every change sent to players, deltas apply on top of the version just before theirs.
+ */
+version: number, guesses: Array<GuessResult>, currentTurn: string, gameStatus: GameStatus, keyboardStatus: { [key in string]?: GameColor }, players: Array<PublicPlayer>, host: string, locked: boolean, public: boolean, settings: GameSettings, };
+
+export type ChatEntry = { from: string, text: string, 
+/**
+ * Unix time in milliseconds.
+ */
+ts: number, };
+
+export type ClientMessage = { "action": "connect", gameId?: string | null, resumeToken?: string | null, userToken?: string | null, displayName?: string | null, avatarColor?: string | null, password?: string | null, protocolVersion?: number | null, } | { "action": "joinGame", gameId: string, password?: string | null, } | { "action": "guessWord", word: string, } | { "action": "createGame", password?: string | null, public?: boolean, } | { "action": "newGame" } | { "action": "disconnectPlayer" } | { "action": "kickPlayer", handle: string, } | { "action": "lockRoom", locked: boolean, } | { "action": "setPublic", public: boolean, } | { "action": "updateSettings", settings: GameSettings, } | { "action": "transferHost", handle: string, } | { "action": "listRooms" } | { "action": "findMatch", mode: GameMode, wordLength?: number | null, } | { "action": "cancelMatch" } | { "action": "getStats", userId?: string | null, } | { "action": "chat", text: string, } | { "action": "draftUpdate", letters: string, } | { "action": "react", guessIndex: number, emoji: string, } | { "action": "suggestWord", word: string, } | { "action": "acceptSuggestion", from: string, } | { "action": "getLeaderboard", board: LeaderboardKind, window?: LeaderboardWindow, 
+/**
+ * Only count rounds played in this mode, ratings are always competitive.
+ */
+mode?: GameMode | null, } | { "action": "getSnapshot" };
+
+/**
+ * Any client message, optionally tagged with an id that the `Ack` or `Error` answering it will carry.
+ */
+export type ClientRequest = { requestId?: string | null, } & ({ "action": "connect", gameId?: string | null, resumeToken?: string | null, userToken?: string | null, displayName?: string | null, avatarColor?: string | null, password?: string | null, protocolVersion?: number | null, } | { "action": "joinGame", gameId: string, password?: string | null, } | { "action": "guessWord", word: string, } | { "action": "createGame", password?: string | null, public?: boolean, } | { "action": "newGame" } | { "action": "disconnectPlayer" } | { "action": "kickPlayer", handle: string, } | { "action": "lockRoom", locked: boolean, } | { "action": "setPublic", public: boolean, } | { "action": "updateSettings", settings: GameSettings, } | { "action": "transferHost", handle: string, } | { "action": "listRooms" } | { "action": "findMatch", mode: GameMode, wordLength?: number | null, } | { "action": "cancelMatch" } | { "action": "getStats", userId?: string | null, } | { "action": "chat", text: string, } | { "action": "draftUpdate", letters: string, } | { "action": "react", guessIndex: number, emoji: string, } | { "action": "suggestWord", word: string, } | { "action": "acceptSuggestion", from: string, } | { "action": "getLeaderboard", board: LeaderboardKind, window?: LeaderboardWindow, 
+/**
+ * Only count rounds played in this mode, ratings are always competitive.
+ */
+mode?: GameMode | null, } | { "action": "getSnapshot" });
+
+export type GameColor = "gray" | "yellow" | "green";
+
+export type GameError = { "type": "stopGame" } | { "type": "joinError", message: string, } | { "type": "passwordError", message: string, } | { "type": "guessError", message: string, } | { "type": "invalidResumeToken", message: string, } | { "type": "expiredResumeToken", message: string, } | { "type": "profileError", message: string, } | { "type": "permissionDenied", message: string, } | { "type": "playerNotFound", message: string, } | { "type": "settingsError", message: string, } | { "type": "chatError", message: string, } | { "type": "reactionError", message: string, } | { "type": "unsupportedProtocol", message: string, minVersion: number, maxVersion: number, } | { "type": "rateLimitError", message: string, } | { "type": "invalidMessage", message: string, } | { "type": "unknownAction", message: string, action: string, } | { "type": "notConnected", message: string, } | { "type": "notInGame", message: string, };
+
+export type GameMode = "coop" | "competitive" | "daily";
+
+export type GameSettings = { mode: GameMode, hardMode: boolean, wordLength: number, };
+
+export type GameStatus = "waiting" | "inProgress" | "won" | "lost";
+
+export type GuessResult = { word: string, status: Array<GameColor>, 
+/**
+ * How many players reacted to this row with each emoji.
+ */
+reactions: { [key in string]?: number }, };
+
+export type LeaderboardEntry = { 
+/**
+ * Players on the same value share a rank.
+ */
+rank: number, userId: string, username: string, value: number, roundsPlayed: number, };
+
+export type LeaderboardKind = "averageGuesses" | "longestStreak" | "rating";
+
+export type LeaderboardWindow = "allTime" | "weekly";
+
+export type PlayerStats = { gamesPlayed: number, gamesWon: number, winPercentage: number, currentStreak: number, maxStreak: number, 
+/**
+ * How many rounds were won in 1, 2, ... `MAX_GUESSES` guesses.
+ */
+guessDistribution: Array<number>, };
+
+export type PublicPlayer = { handle: string, displayName: string, avatarColor: string | null, away: boolean, };
+
+/**
+ * What the lobby knows about a room, pushed by the game actor whenever it changes.
+ */
+export type RoomSummary = { gameId: string, playerCount: number, mode: GameMode, wordLength: number, status: GameStatus, hasPassword: boolean, };
+
+export type ServerMessage = { "status": "created", gameStatus: GameStatus, gameId: string, } | { "status": "joined", boardState: BoardState, gameId: string, solution: string | null, } | { "status": "gameUpdate", boardState: BoardState, solution: string | null, } | { "status": "newGame", boardState: BoardState, } | { "status": "welcome", playerId: string, handle: string, message: string, 
+/**
+ * Long lived identity that ratings and stats are kept under, send it back on the next connect.
+ */
+userToken: string, protocolVersion: number, features: Array<string>, } | { "status": "resumeToken", resumeToken: string, } | { "status": "error", error: GameError, 
+/**
+ * The `requestId` of the command that failed, when it carried one.
+ */
+requestId?: string | null, } | { "status": "ack", requestId: string, } | { "status": "exited", boardState: BoardState, } | { "status": "serverShuttingDown", reconnectAfter: number, } | { "status": "roomClosed", reason: string, 
+/**
+ * Rooms closed for a restart can be joined again once the server is back.
+ */
+resumable: boolean, } | { "status": "kicked", gameId: string, } | { "status": "roomList", rooms: Array<RoomSummary>, } | { "status": "stats", userId: string, stats: PlayerStats, } | { "status": "leaderboard", entries: Array<LeaderboardEntry>, board: LeaderboardKind, window?: LeaderboardWindow, 
+/**
+ * Only count rounds played in this mode, ratings are always competitive.
+ */
+mode?: GameMode | null, } | { "status": "chatMessage", from: string, text: string, 
+/**
+ * Unix time in milliseconds.
+ */
+ts: number, } | { "status": "draft", from: string, letters: string, } | { "status": "suggestion", from: string, word: string, } | { "status": "boardDelta", version: number, changes: Array<BoardChange>, } | { "status": "reaction", version: number, guessIndex: number, emoji: string, count: number, from: string, } | { "status": "matchQueued", position: number, } | { "status": "matchCancelled" } | { "status": "matchTimedOut" };
+
+export const PROTOCOL_VERSION = 2;
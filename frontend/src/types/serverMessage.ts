@@ -1,29 +1,17 @@
-export type KeyColor = 'gray' | 'yellow' | 'green';
-export type GameState = 'waiting' | 'inProgress' | 'won' | 'lost' | "pending";
+import type { GameError, GameStatus, ServerMessage } from "./protocol.ts";
 
-export interface Guess {
-	word: string,
-	status: KeyColor[],
-}
+export type {
+	BoardChange, BoardState, ChatEntry, GameMode, GameSettings, LeaderboardEntry, LeaderboardKind, LeaderboardWindow,
+	PlayerStats, PublicPlayer, RoomSummary, ServerMessage,
+} from "./protocol.ts";
+export type { GameColor as KeyColor, GuessResult as Guess } from "./protocol.ts";
+
+export type GameState = GameStatus | "pending";
 
-interface BoardState {
-	guesses: Guess[],
-	currentTurn: string,
-	gameStatus: GameState,
-	keyboardStatus: { [key: string]: KeyColor },
-	players: string[],
-};
+export type Suggestion = Omit<Extract<ServerMessage, { status: "suggestion" }>, "status">;
 
+// What a toast shows, every error the server sends to players carries a message.
 export interface Error {
-	type: "joinError" | "guessError" | "rateLimitError",
+	type: GameError["type"],
 	message: string,
 }
-
-export type ServerMessage =
-	| { status: 'created', gameStatus: GameState, gameId: string, }
-	| { status: 'joined', boardState: BoardState, gameId: string, solution: string | null, }
-	| { status: 'gameUpdate', boardState: BoardState, solution: string | null, }
-	| { status: 'newGame', boardState: BoardState }
-	| { status: 'welcome', playerId: string, message: string }
-	| { status: 'error', error: Error }
-	| { status: "exited", boardState: BoardState };